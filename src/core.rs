@@ -1,5 +1,7 @@
 pub mod bus;
 pub mod db;
 pub mod message;
+pub mod metrics;
 pub mod si;
+pub mod timezone;
 pub mod value;
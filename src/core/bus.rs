@@ -3,6 +3,7 @@
 //! The bus implements many-producer-many-consumer queue and allows each service
 //! to listen to each other service.
 
+use crate::core::metrics;
 use crate::prelude::*;
 
 pub struct Bus {
@@ -44,6 +45,7 @@ impl Bus {
         task::spawn(async move {
             while let Some(message) = self.producer_rx.next().await {
                 Self::log_message(&message);
+                metrics::record_bus_message();
                 for tx in self.consumers.iter_mut() {
                     message.clone().send_to(tx).await;
                 }
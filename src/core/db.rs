@@ -1,16 +1,43 @@
 //! Database interface.
 
 use chrono::prelude::*;
-use sqlx::sqlite::{SqliteConnectOptions, SqliteDone, SqliteJournalMode, SqliteRow};
-use sqlx::{query, query_scalar, Row, SqliteConnection, SqlitePool};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteConnectOptions, SqliteDone, SqliteJournalMode, SqliteRow};
+use sqlx::{query, query_scalar, Row, Sqlite, SqliteConnection, SqlitePool};
 
+use crate::core::db::annotation::Annotation;
+use crate::core::db::media_reading::{MediaReading, MediaValue};
+use crate::core::db::push_subscription::{PushSubscription, PushSubscriptionKeys};
+use crate::core::db::reading_query::ReadingQuery;
+use crate::core::db::sensor_summary::SensorSummary;
+use crate::core::db::statistics::{Aggregate, Statistics};
 use crate::prelude::*;
 
+pub mod annotation;
+pub mod media_reading;
 pub mod migrations;
+pub mod push_subscription;
 pub mod reading;
+pub mod reading_query;
 pub mod sensor;
+pub mod sensor_summary;
+pub mod statistics;
 pub mod tasks;
 
+/// The serialized blob starts with the variant tag and the content length.
+const BLOB_HEADER_LENGTH: usize = 12;
+
+/// Number of the first blob bytes, which are needed to sniff the MIME type.
+const BLOB_HEAD_LENGTH: usize = 16;
+
+/// Matches the sensor readings against `ReadingQuery`, the parameters are bound by `bind_reading_query`.
+// language=sql
+const READING_QUERY_CONDITION: &str = r#"
+    sensor_fk = ? AND timestamp >= ? AND timestamp < ?
+    AND (? IS NULL OR numeric_value >= ?)
+    AND (? IS NULL OR numeric_value <= ?)
+"#;
+
 /// Wraps the connection and provides the high-level database methods.
 #[derive(Clone)]
 pub struct Connection {
@@ -46,6 +73,9 @@ impl Connection {
                         .await
                         .try_collect::<SqliteDone>()
                         .await?;
+                    if i + 1 == migrations::NUMERIC_VALUE_VERSION {
+                        Self::fill_numeric_values(&mut transaction).await?;
+                    }
                     transaction.commit().await?;
                 }
 
@@ -66,21 +96,61 @@ impl Connection {
             .unwrap())
     }
 
+    /// Fills in the numeric values of the readings stored before the column was added.
+    async fn fill_numeric_values(connection: &mut SqliteConnection) -> Result {
+        // language=sql
+        const SELECT_QUERY: &str = "SELECT sensor_fk, timestamp, value FROM readings";
+        // language=sql
+        const UPDATE_QUERY: &str = "UPDATE readings SET numeric_value = ? WHERE sensor_fk = ? AND timestamp = ?";
+
+        // Only the numeric values are kept in memory, the blobs are dropped as they're read.
+        let numeric_values: Vec<(f64, i64, i64)> = query(SELECT_QUERY)
+            .try_map(|row: SqliteRow| {
+                let value = bincode::deserialize(&row.try_get::<Vec<u8>, _>("value")?).unwrap_or(Value::Other);
+                Ok(match statistics::numeric_value(&value) {
+                    Some(numeric_value) => Some((numeric_value, row.try_get("sensor_fk")?, row.try_get("timestamp")?)),
+                    None => None,
+                })
+            })
+            .fetch(&mut *connection)
+            .try_filter_map(|numeric_value| future::ready(Ok(numeric_value)))
+            .try_collect()
+            .await?;
+        info!("Filling in {} numeric values…", numeric_values.len());
+        for (numeric_value, sensor_fk, timestamp) in numeric_values.into_iter() {
+            query(UPDATE_QUERY)
+                .bind(numeric_value)
+                .bind(sensor_fk)
+                .bind(timestamp)
+                .execute(&mut *connection)
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn upsert_message_to(connection: &mut SqliteConnection, message: &Message) -> Result {
         let sensor_pk = hash_sensor_id(&message.sensor.id);
         let timestamp = message.reading.timestamp.timestamp_millis();
         let value = bincode::serialize(&message.reading.value)?;
+        let numeric_value = statistics::numeric_value(&message.reading.value);
 
         query(
             // language=sql
             r#"
                 -- noinspection SqlResolve @ any/"excluded"
-                REPLACE INTO sensors (pk, sensor_id, title, timestamp, location, value, is_writable)
-                VALUES (?, ?, ?, ?, ?, ?, ?);
+                INSERT INTO sensors (pk, sensor_id, title, timestamp, location, value, is_writable)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (pk) DO UPDATE SET
+                    sensor_id = excluded.sensor_id,
+                    title = excluded.title,
+                    timestamp = excluded.timestamp,
+                    location = excluded.location,
+                    value = excluded.value,
+                    is_writable = excluded.is_writable;
 
                 -- noinspection SqlResolve @ any/"excluded"
-                REPLACE INTO readings (sensor_fk, timestamp, value)
-                VALUES (?, ?, ?);
+                REPLACE INTO readings (sensor_fk, timestamp, value, numeric_value)
+                VALUES (?, ?, ?, ?);
             "#,
         )
         .bind(sensor_pk)
@@ -93,6 +163,7 @@ impl Connection {
         .bind(sensor_pk)
         .bind(timestamp)
         .bind(&value)
+        .bind(numeric_value)
         .execute(connection)
         .await?;
 
@@ -124,13 +195,15 @@ impl Connection {
         Ok(())
     }
 
-    /// Selects the latest readings for all sensors.
+    /// Selects the latest readings for all sensors, except the hidden ones.
     pub async fn select_actuals(&self) -> Result<Vec<(Sensor, Reading)>> {
         // language=sql
-        Ok(query(r"SELECT * FROM sensors ORDER BY location, sensor_id")
-            .try_map(get_sensor_reading)
-            .fetch_all(&self.inner)
-            .await?)
+        Ok(
+            query(r"SELECT * FROM sensors WHERE NOT is_hidden ORDER BY location, sensor_id")
+                .try_map(get_sensor_reading)
+                .fetch_all(&self.inner)
+                .await?,
+        )
     }
 
     /// Selects the database size.
@@ -153,15 +226,108 @@ impl Connection {
             .await?)
     }
 
-    pub async fn delete_sensor(&self, sensor_id: &str) -> Result {
+    /// Selects all the sensors, including the hidden ones, alongside with their storage statistics.
+    pub async fn select_sensor_summaries(&self) -> Result<Vec<SensorSummary>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT
+                sensors.*,
+                COUNT(readings.sensor_fk) AS reading_count,
+                COALESCE(SUM(LENGTH(readings.value)), 0) AS reading_size
+            FROM sensors
+            LEFT JOIN readings ON readings.sensor_fk = sensors.pk
+            GROUP BY sensors.pk
+            ORDER BY sensors.location, sensors.sensor_id
+        "#;
+        Ok(query(QUERY).try_map(get_sensor_summary).fetch_all(&self.inner).await?)
+    }
+
+    /// Selects the specified sensor alongside with its storage statistics.
+    ///
+    /// Unlike `select_sensor_summaries`, it only scans the readings of the sensor.
+    pub async fn select_sensor_summary(&self, sensor_id: &str) -> Result<Option<SensorSummary>> {
         // language=sql
-        query(r"DELETE FROM sensors WHERE sensor_id = ?")
+        const QUERY: &str = r#"
+            SELECT
+                sensors.*,
+                (SELECT COUNT(*) FROM readings WHERE sensor_fk = sensors.pk) AS reading_count,
+                (SELECT COALESCE(SUM(LENGTH(value)), 0) FROM readings WHERE sensor_fk = sensors.pk) AS reading_size
+            FROM sensors
+            WHERE sensor_id = ?
+        "#;
+        Ok(query(QUERY)
             .bind(sensor_id)
-            .execute(&self.inner)
-            .await?;
+            .try_map(get_sensor_summary)
+            .fetch_optional(&self.inner)
+            .await?)
+    }
+
+    /// Deletes the sensor alongside with all its readings.
+    pub async fn delete_sensor(&self, sensor_id: &str) -> Result {
+        self.delete_sensors(&[sensor_id]).await
+    }
+
+    /// Deletes the sensors alongside with all their readings within a single transaction.
+    pub async fn delete_sensors<S: AsRef<str>>(&self, sensor_ids: &[S]) -> Result {
+        // language=sql
+        const QUERY: &str = r#"
+            DELETE FROM sensors WHERE pk = ?;
+            DELETE FROM readings WHERE sensor_fk = ?;
+        "#;
+        let mut transaction = self.inner.begin().await?;
+        for sensor_id in sensor_ids.iter() {
+            let sensor_pk = hash_sensor_id(sensor_id.as_ref());
+            query(QUERY)
+                .bind(sensor_pk)
+                .bind(sensor_pk)
+                .execute(&mut transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Hides the sensors from the dashboard or shows them back.
+    pub async fn set_sensors_hidden<S: AsRef<str>>(&self, sensor_ids: &[S], is_hidden: bool) -> Result {
+        // language=sql
+        const QUERY: &str = "UPDATE sensors SET is_hidden = ? WHERE pk = ?";
+        let mut transaction = self.inner.begin().await?;
+        for sensor_id in sensor_ids.iter() {
+            query(QUERY)
+                .bind(is_hidden)
+                .bind(hash_sensor_id(sensor_id.as_ref()))
+                .execute(&mut transaction)
+                .await?;
+        }
+        transaction.commit().await?;
         Ok(())
     }
 
+    /// Deletes the sensor readings older than the specified timestamp.
+    /// The sensors themselves are kept, as well as their latest readings.
+    ///
+    /// Returns the number of deleted readings.
+    pub async fn delete_readings_older_than<S: AsRef<str>>(
+        &self,
+        sensor_ids: &[S],
+        before: &DateTime<Local>,
+    ) -> Result<u64> {
+        // language=sql
+        const QUERY: &str = "DELETE FROM readings WHERE sensor_fk = ? AND timestamp < ?";
+        let mut transaction = self.inner.begin().await?;
+        let mut deleted_count = 0;
+        for sensor_id in sensor_ids.iter() {
+            deleted_count += query(QUERY)
+                .bind(hash_sensor_id(sensor_id.as_ref()))
+                .bind(before.timestamp_millis())
+                .execute(&mut transaction)
+                .await?
+                .rows_affected();
+        }
+        transaction.commit().await?;
+        Ok(deleted_count)
+    }
+
     /// Selects the specified sensor readings within the specified period.
     pub async fn select_readings(&self, sensor_id: &str, since: &DateTime<Local>) -> Result<Vec<Reading>> {
         // language=sql
@@ -179,6 +345,356 @@ impl Connection {
             .await?)
     }
 
+    /// Selects the specified sensor readings within the half-open `[since, until)` range.
+    pub async fn select_readings_between(
+        &self,
+        sensor_id: &str,
+        since: &DateTime<Local>,
+        until: &DateTime<Local>,
+    ) -> Result<Vec<Reading>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT timestamp, value
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ?
+            ORDER BY timestamp
+        "#;
+        Ok(query(QUERY)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(since.timestamp_millis())
+            .bind(until.timestamp_millis())
+            .try_map(get_reading)
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
+    /// Counts the sensor readings matching the query.
+    pub async fn select_reading_count_by(&self, sensor_id: &str, reading_query: &ReadingQuery) -> Result<i64> {
+        let sql = format!("SELECT COUNT(*) FROM readings WHERE {}", READING_QUERY_CONDITION);
+        Ok(bind_reading_query(query(&sql), sensor_id, reading_query)
+            .try_map(|row: SqliteRow| row.try_get(0))
+            .fetch_one(&self.inner)
+            .await?)
+    }
+
+    /// Selects the sensor readings matching the query, sorted as requested.
+    ///
+    /// `limit` and `offset` select the page, `None` means all the readings.
+    pub async fn select_readings_by(
+        &self,
+        sensor_id: &str,
+        reading_query: &ReadingQuery,
+        limit: Option<i64>,
+        offset: i64,
+    ) -> Result<Vec<Reading>> {
+        let direction = if reading_query.descending { "DESC" } else { "ASC" };
+        let order = if reading_query.by_value {
+            // Non-numeric values are compared by their serialized contents, which follow the variant tag and the length.
+            // That is the text order for the strings.
+            // SQL strings are 1-indexed.
+            format!(
+                "numeric_value {0}, SUBSTR(value, {1}) {0}, value {0}, timestamp {0}",
+                direction,
+                BLOB_HEADER_LENGTH + 1,
+            )
+        } else {
+            format!("timestamp {}", direction)
+        };
+        let sql = format!(
+            "SELECT timestamp, value FROM readings WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+            READING_QUERY_CONDITION, order
+        );
+        Ok(bind_reading_query(query(&sql), sensor_id, reading_query)
+            .bind(limit.unwrap_or(-1))
+            .bind(offset)
+            .try_map(get_reading)
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
+    /// Selects the images and the binary readings within the half-open `[since, until)` range, the latest first.
+    ///
+    /// Only the heads of the blobs are selected, the serialized values are distinguished by their variant tags.
+    pub async fn select_media_readings_between(
+        &self,
+        sensor_id: &str,
+        since: &DateTime<Local>,
+        until: &DateTime<Local>,
+    ) -> Result<Vec<MediaReading>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT
+                timestamp,
+                CASE WHEN SUBSTR(value, 1, 4) = ? THEN value ELSE SUBSTR(value, 1, ?) END AS head,
+                LENGTH(value) AS length
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND SUBSTR(value, 1, 4) IN (?, ?)
+            ORDER BY timestamp DESC
+        "#;
+        let image_url_tag = value_tag(&Value::ImageUrl(String::new()))?;
+        let blob_tag = value_tag(&Value::Blob(Default::default()))?;
+        Ok(query(QUERY)
+            .bind(&image_url_tag)
+            .bind((BLOB_HEADER_LENGTH + BLOB_HEAD_LENGTH) as i64)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(since.timestamp_millis())
+            .bind(until.timestamp_millis())
+            .bind(&image_url_tag)
+            .bind(&blob_tag)
+            .try_map(|row: SqliteRow| {
+                let head: Vec<u8> = row.try_get("head")?;
+                let length: i64 = row.try_get("length")?;
+                let value = if head.starts_with(&image_url_tag) {
+                    match bincode::deserialize(&head) {
+                        Ok(Value::ImageUrl(url)) => MediaValue::ImageUrl(url),
+                        _ => MediaValue::ImageUrl(String::new()),
+                    }
+                } else {
+                    MediaValue::Blob {
+                        head: Bytes::from(head.get(BLOB_HEADER_LENGTH..).unwrap_or_default().to_vec()),
+                        size: (length as usize).saturating_sub(BLOB_HEADER_LENGTH),
+                    }
+                };
+                Ok(MediaReading {
+                    timestamp: Local.timestamp_millis(row.try_get("timestamp")?),
+                    value,
+                })
+            })
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
+    /// Calculates the statistics of the sensor readings within the half-open `[since, until)` range.
+    ///
+    /// The readings aren't loaded: the database does the math.
+    /// Returns `None` if there're no numeric readings within the range.
+    pub async fn select_statistics(
+        &self,
+        sensor_id: &str,
+        since: &DateTime<Local>,
+        until: &DateTime<Local>,
+    ) -> Result<Option<Statistics>> {
+        // language=sql
+        const AGGREGATES_QUERY: &str = r#"
+            SELECT
+                COUNT(*) AS count,
+                MIN(numeric_value) AS min,
+                MAX(numeric_value) AS max,
+                AVG(numeric_value) AS mean,
+                AVG(numeric_value * numeric_value) AS mean_square
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND numeric_value IS NOT NULL
+        "#;
+        // language=sql
+        const FIRST_QUERY: &str = r#"
+            SELECT timestamp, value, numeric_value
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND numeric_value IS NOT NULL
+            ORDER BY timestamp
+            LIMIT 1
+        "#;
+        // language=sql
+        const LAST_QUERY: &str = r#"
+            SELECT timestamp, value, numeric_value
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND numeric_value IS NOT NULL
+            ORDER BY timestamp DESC
+            LIMIT 1
+        "#;
+        // The middle value or the two middle values.
+        // language=sql
+        const MEDIAN_QUERY: &str = r#"
+            SELECT AVG(numeric_value)
+            FROM (
+                SELECT numeric_value
+                FROM readings
+                WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND numeric_value IS NOT NULL
+                ORDER BY numeric_value
+                LIMIT ? OFFSET ?
+            )
+        "#;
+        // After a reset, the counter starts over from zero.
+        // language=sql
+        const CONSUMPTION_QUERY: &str = r#"
+            SELECT COALESCE(SUM(CASE WHEN increment >= 0 THEN increment ELSE numeric_value END), 0.0)
+            FROM (
+                SELECT numeric_value, numeric_value - LAG(numeric_value) OVER (ORDER BY timestamp) AS increment
+                FROM readings
+                WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ? AND numeric_value IS NOT NULL
+            )
+            WHERE increment IS NOT NULL
+        "#;
+
+        let sensor_fk = hash_sensor_id(sensor_id);
+        let since = since.timestamp_millis();
+        let until = until.timestamp_millis();
+
+        let aggregates = query(AGGREGATES_QUERY)
+            .bind(sensor_fk)
+            .bind(since)
+            .bind(until)
+            .try_map(|row: SqliteRow| {
+                Ok((
+                    row.try_get::<i64, _>("count")?,
+                    row.try_get::<Option<f64>, _>("min")?,
+                    row.try_get::<Option<f64>, _>("max")?,
+                    row.try_get::<Option<f64>, _>("mean")?,
+                    row.try_get::<Option<f64>, _>("mean_square")?,
+                ))
+            })
+            .fetch_optional(&self.inner)
+            .await?;
+        let (count, min, max, mean, mean_square) = match aggregates {
+            Some((count, Some(min), Some(max), Some(mean), Some(mean_square))) if count != 0 => {
+                (count, min, max, mean, mean_square)
+            }
+            _ => return Ok(None),
+        };
+        let first = query(FIRST_QUERY)
+            .bind(sensor_fk)
+            .bind(since)
+            .bind(until)
+            .try_map(get_numeric_reading)
+            .fetch_optional(&self.inner)
+            .await?;
+        let last = query(LAST_QUERY)
+            .bind(sensor_fk)
+            .bind(since)
+            .bind(until)
+            .try_map(get_numeric_reading)
+            .fetch_optional(&self.inner)
+            .await?;
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(None),
+        };
+        let median: f64 = query_scalar(MEDIAN_QUERY)
+            .bind(sensor_fk)
+            .bind(since)
+            .bind(until)
+            .bind(2 - count % 2)
+            .bind((count - 1) / 2)
+            .fetch_one(&self.inner)
+            .await?;
+        let consumption: Option<f64> = if statistics::is_cumulative(&first.0.value) {
+            Some(
+                query_scalar(CONSUMPTION_QUERY)
+                    .bind(sensor_fk)
+                    .bind(since)
+                    .bind(until)
+                    .fetch_one(&self.inner)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        let delta = last.1 - first.1;
+        Ok(Some(Statistics {
+            count: count as usize,
+            min,
+            max,
+            mean,
+            median,
+            // The rounding errors may turn a zero variance slightly negative.
+            std_dev: (mean_square - mean * mean).max(0.0).sqrt(),
+            rate_per_hour: statistics::rate_per_hour(&first.0, &last.0, delta),
+            first: first.0,
+            last: last.0,
+            delta,
+            consumption,
+        }))
+    }
+
+    /// Calculates the aggregate of the sensor readings within the half-open `[since, until)` range.
+    ///
+    /// Returns `None` if there're no numeric readings within the range.
+    pub async fn select_aggregate(
+        &self,
+        sensor_id: &str,
+        aggregate: Aggregate,
+        since: &DateTime<Local>,
+        until: &DateTime<Local>,
+    ) -> Result<Option<f64>> {
+        // language=sql
+        const MEAN_QUERY: &str = r#"
+            SELECT AVG(numeric_value)
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ?
+        "#;
+        // language=sql
+        const MIN_QUERY: &str = r#"
+            SELECT MIN(numeric_value)
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ?
+        "#;
+        // language=sql
+        const MAX_QUERY: &str = r#"
+            SELECT MAX(numeric_value)
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp >= ? AND timestamp < ?
+        "#;
+        let sql = match aggregate {
+            Aggregate::Mean => MEAN_QUERY,
+            Aggregate::Min => MIN_QUERY,
+            Aggregate::Max => MAX_QUERY,
+        };
+        Ok(query_scalar(sql)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(since.timestamp_millis())
+            .bind(until.timestamp_millis())
+            .fetch_one(&self.inner)
+            .await?)
+    }
+
+    /// Selects the latest sensor reading before the specified timestamp.
+    ///
+    /// It's used to find out the sensor state at the beginning of a period.
+    pub async fn select_last_reading_before(
+        &self,
+        sensor_id: &str,
+        before: &DateTime<Local>,
+    ) -> Result<Option<Reading>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT timestamp, value
+            FROM readings
+            WHERE sensor_fk = ? AND timestamp < ?
+            ORDER BY timestamp DESC
+            LIMIT 1
+        "#;
+        Ok(query(QUERY)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(before.timestamp_millis())
+            .try_map(get_reading)
+            .fetch_optional(&self.inner)
+            .await?)
+    }
+
+    /// Selects the sensor reading with exactly the specified timestamp.
+    pub async fn select_reading(&self, sensor_id: &str, timestamp: &DateTime<Local>) -> Result<Option<Reading>> {
+        // language=sql
+        const QUERY: &str = "SELECT timestamp, value FROM readings WHERE sensor_fk = ? AND timestamp = ?";
+        Ok(query(QUERY)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(timestamp.timestamp_millis())
+            .try_map(get_reading)
+            .fetch_optional(&self.inner)
+            .await?)
+    }
+
+    /// Selects the distinct values of the sensor, for example, to list possible `StringEnum` options.
+    pub async fn select_distinct_values(&self, sensor_id: &str, limit: i64) -> Result<Vec<Value>> {
+        // language=sql
+        const QUERY: &str = "SELECT DISTINCT value FROM readings WHERE sensor_fk = ? LIMIT ?";
+        Ok(query(QUERY)
+            .bind(hash_sensor_id(sensor_id))
+            .bind(limit)
+            .try_map(|row: SqliteRow| Ok(bincode::deserialize(&row.try_get::<Vec<u8>, _>(0)?).unwrap_or(Value::Other)))
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
     pub async fn select_last_n_readings(&self, sensor_id: &str, limit: i64) -> Result<Vec<Reading>> {
         // language=sql
         const QUERY: &str = "SELECT timestamp, value FROM readings WHERE sensor_fk = ? ORDER BY timestamp LIMIT ?";
@@ -238,6 +754,15 @@ impl Connection {
         Ok(())
     }
 
+    pub async fn delete_user_data(&self, key: &str) -> Result {
+        // language=sql
+        query("DELETE FROM user_data WHERE pk = ?")
+            .bind(key)
+            .execute(&self.inner)
+            .await?;
+        Ok(())
+    }
+
     pub async fn get_user_data<V: DeserializeOwned + Send + Unpin>(&self, key: &str) -> Result<Option<V>> {
         // language=sql
         const QUERY: &str = r#"
@@ -251,6 +776,142 @@ impl Connection {
             .fetch_optional(&self.inner)
             .await?)
     }
+
+    /// Stores the browser push subscription of the service, replacing the existing one with the same endpoint.
+    pub async fn upsert_push_subscription(&self, service_id: &str, subscription: &PushSubscription) -> Result {
+        // language=sql
+        const QUERY: &str = r#"
+            REPLACE INTO push_subscriptions (endpoint, service_id, p256dh, auth, created_at) VALUES (?, ?, ?, ?, ?)
+        "#;
+        query(QUERY)
+            .bind(&subscription.endpoint)
+            .bind(service_id)
+            .bind(&subscription.keys.p256dh)
+            .bind(&subscription.keys.auth)
+            .bind(Local::now().timestamp_millis())
+            .execute(&self.inner)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn select_push_subscriptions(&self, service_id: &str) -> Result<Vec<PushSubscription>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT endpoint, p256dh, auth FROM push_subscriptions
+            WHERE service_id = ?
+            ORDER BY created_at
+        "#;
+        Ok(query(QUERY)
+            .bind(service_id)
+            .try_map(|row: SqliteRow| {
+                Ok(PushSubscription {
+                    endpoint: row.try_get("endpoint")?,
+                    keys: PushSubscriptionKeys {
+                        p256dh: row.try_get("p256dh")?,
+                        auth: row.try_get("auth")?,
+                    },
+                })
+            })
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
+    pub async fn delete_push_subscription(&self, endpoint: &str) -> Result {
+        // language=sql
+        query("DELETE FROM push_subscriptions WHERE endpoint = ?")
+            .bind(endpoint)
+            .execute(&self.inner)
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the annotation and returns its ID.
+    pub async fn insert_annotation(&self, annotation: &Annotation) -> Result<i64> {
+        // language=sql
+        const QUERY: &str = r#"
+            INSERT INTO annotations (sensor_id, location, text, author, since, until, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#;
+        Ok(query(QUERY)
+            .bind(&annotation.sensor_id)
+            .bind(&annotation.location)
+            .bind(&annotation.text)
+            .bind(&annotation.author)
+            .bind(annotation.since.timestamp_millis())
+            .bind(annotation.until.timestamp_millis())
+            .bind(Local::now().timestamp_millis())
+            .execute(&self.inner)
+            .await?
+            .last_insert_rowid())
+    }
+
+    /// Selects the annotations of the sensor, of its location and the global ones,
+    /// which overlap the half-open `[since, until)` range.
+    pub async fn select_annotations(
+        &self,
+        sensor_id: &str,
+        location: &str,
+        since: &DateTime<Local>,
+        until: &DateTime<Local>,
+    ) -> Result<Vec<Annotation>> {
+        // language=sql
+        const QUERY: &str = r#"
+            SELECT * FROM annotations
+            WHERE
+                (sensor_id = ? OR location = ? OR (sensor_id IS NULL AND location IS NULL))
+                AND since < ? AND until >= ?
+            ORDER BY since
+        "#;
+        Ok(query(QUERY)
+            .bind(sensor_id)
+            .bind(location)
+            .bind(until.timestamp_millis())
+            .bind(since.timestamp_millis())
+            .try_map(|row: SqliteRow| {
+                Ok(Annotation {
+                    id: row.try_get("pk")?,
+                    sensor_id: row.try_get("sensor_id")?,
+                    location: row.try_get("location")?,
+                    text: row.try_get("text")?,
+                    author: row.try_get("author")?,
+                    since: Local.timestamp_millis(row.try_get("since")?),
+                    until: Local.timestamp_millis(row.try_get("until")?),
+                })
+            })
+            .fetch_all(&self.inner)
+            .await?)
+    }
+
+    /// Deletes the annotation and tells whether it has existed.
+    pub async fn delete_annotation(&self, id: i64) -> Result<bool> {
+        // language=sql
+        let deleted_count = query("DELETE FROM annotations WHERE pk = ?")
+            .bind(id)
+            .execute(&self.inner)
+            .await?
+            .rows_affected();
+        Ok(deleted_count != 0)
+    }
+
+    /// Deletes the annotation, if it's the one of the sensor, of its location or a global one,
+    /// and tells whether it has existed.
+    pub async fn delete_sensor_annotation(&self, id: i64, sensor_id: &str, location: &str) -> Result<bool> {
+        // language=sql
+        const QUERY: &str = r#"
+            DELETE FROM annotations
+            WHERE
+                pk = ?
+                AND (sensor_id = ? OR location = ? OR (sensor_id IS NULL AND location IS NULL))
+        "#;
+        let deleted_count = query(QUERY)
+            .bind(id)
+            .bind(sensor_id)
+            .bind(location)
+            .execute(&self.inner)
+            .await?
+            .rows_affected();
+        Ok(deleted_count != 0)
+    }
 }
 
 /// Hashes the sensor ID, hash is then used for a sensor primary key.
@@ -273,6 +934,27 @@ fn get_sensor(row: &SqliteRow) -> StdResult<Sensor, sqlx::Error> {
     })
 }
 
+/// Binds the parameters of `READING_QUERY_CONDITION`.
+fn bind_reading_query<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    sensor_id: &str,
+    reading_query: &ReadingQuery,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    query
+        .bind(hash_sensor_id(sensor_id))
+        .bind(reading_query.since.timestamp_millis())
+        .bind(reading_query.until.timestamp_millis())
+        .bind(reading_query.min)
+        .bind(reading_query.min)
+        .bind(reading_query.max)
+        .bind(reading_query.max)
+}
+
+/// Returns the variant tag, which the serialized value starts with.
+fn value_tag(value: &Value) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(value)?[..4].to_vec())
+}
+
 /// Builds a `Reading` instance based on the database row.
 fn get_reading<R: Borrow<SqliteRow>>(row: R) -> StdResult<Reading, sqlx::Error> {
     let row = row.borrow();
@@ -282,11 +964,27 @@ fn get_reading<R: Borrow<SqliteRow>>(row: R) -> StdResult<Reading, sqlx::Error>
     })
 }
 
+/// Builds a `Reading` instance alongside with its numeric value.
+fn get_numeric_reading(row: SqliteRow) -> StdResult<(Reading, f64), sqlx::Error> {
+    Ok((get_reading(&row)?, row.try_get("numeric_value")?))
+}
+
 fn get_sensor_reading<R: Borrow<SqliteRow>>(row: R) -> StdResult<(Sensor, Reading), sqlx::Error> {
     let row = row.borrow();
     Ok((get_sensor(row)?, get_reading(row)?))
 }
 
+/// Builds a `SensorSummary` instance based on the database row.
+fn get_sensor_summary(row: SqliteRow) -> StdResult<SensorSummary, sqlx::Error> {
+    Ok(SensorSummary {
+        sensor: get_sensor(&row)?,
+        reading: get_reading(&row)?,
+        is_hidden: row.try_get("is_hidden")?,
+        reading_count: row.try_get("reading_count")?,
+        reading_size: row.try_get("reading_size")?,
+    })
+}
+
 impl From<Message> for (Sensor, Reading) {
     fn from(message: Message) -> Self {
         (message.sensor, message.reading)
@@ -388,6 +1086,294 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn select_readings_between_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test").value(Value::Counter(42));
+        for timestamp in &[1_566_424_127_000, 1_566_424_128_000, 1_566_424_129_000] {
+            db.upsert_message(&message.clone().timestamp(Local.timestamp_millis(*timestamp)))
+                .await?;
+        }
+        let readings = db
+            .select_readings_between(
+                "test",
+                &Local.timestamp_millis(1_566_424_128_000),
+                &Local.timestamp_millis(1_566_424_129_000),
+            )
+            .await?;
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].timestamp, Local.timestamp_millis(1_566_424_128_000));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_readings_by_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        for (i, value) in [21.0, 23.5, 19.0, 22.0].iter().enumerate() {
+            let message = Message::new("test")
+                .value(Value::Temperature(*value))
+                .timestamp(Local.timestamp_millis(1_566_424_127_000 + i as i64 * 1000));
+            db.upsert_message(&message).await?;
+        }
+        let mut reading_query = ReadingQuery {
+            since: Local.timestamp_millis(1_566_424_127_000),
+            until: Local.timestamp_millis(1_566_424_131_000),
+            min: Some(20.0),
+            max: Some(23.0),
+            by_value: false,
+            descending: true,
+        };
+        assert_eq!(db.select_reading_count_by("test", &reading_query).await?, 2);
+        let values: Vec<Value> = db
+            .select_readings_by("test", &reading_query, None, 0)
+            .await?
+            .into_iter()
+            .map(|reading| reading.value)
+            .collect();
+        assert_eq!(values, vec![Value::Temperature(22.0), Value::Temperature(21.0)]);
+
+        reading_query.min = None;
+        reading_query.max = None;
+        reading_query.by_value = true;
+        assert_eq!(db.select_reading_count_by("test", &reading_query).await?, 4);
+        let readings = db.select_readings_by("test", &reading_query, Some(2), 1).await?;
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[0].value, Value::Temperature(22.0));
+        assert_eq!(readings[1].value, Value::Temperature(21.0));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_statistics_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        for (timestamp, value) in &[(1_566_424_127_000, 20.0), (1_566_424_128_000, 22.0)] {
+            let message = Message::new("test")
+                .value(Value::Temperature(*value))
+                .timestamp(Local.timestamp_millis(*timestamp));
+            db.upsert_message(&message).await?;
+        }
+        db.upsert_message(&Message::new("test").value(Value::Temperature(f64::NAN)))
+            .await?;
+        let (since, until) = (Local.timestamp_millis(0), Local::now());
+        let statistics = db.select_statistics("test", &since, &until).await?.unwrap();
+        assert_eq!(statistics.count, 2);
+        assert_eq!(statistics.mean, 21.0);
+        assert_eq!(statistics.median, 21.0);
+        assert_eq!(statistics.std_dev, 1.0);
+        assert_eq!(statistics.delta, 2.0);
+        assert_eq!(statistics.rate_per_hour, Some(7200.0));
+        assert_eq!(db.select_statistics("missing", &since, &until).await?, None);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_statistics_constant_std_dev_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        for timestamp in 1..=3 {
+            let message = Message::new("test")
+                .value(Value::Temperature(0.1))
+                .timestamp(Local.timestamp_millis(timestamp));
+            db.upsert_message(&message).await?;
+        }
+        let statistics = db
+            .select_statistics("test", &Local.timestamp_millis(0), &Local::now())
+            .await?
+            .unwrap();
+        // The rounding errors must not turn the standard deviation into `NaN`.
+        assert!(statistics.std_dev.abs() < 1e-6);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_aggregate_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        for (timestamp, value) in &[(1_000, Value::Temperature(20.0)), (2_000, Value::Temperature(22.0))] {
+            let message = Message::new("test")
+                .value(value.clone())
+                .timestamp(Local.timestamp_millis(*timestamp));
+            db.upsert_message(&message).await?;
+        }
+        db.upsert_message(&Message::new("test").value(Value::Text("skipped".into())))
+            .await?;
+        let (since, until) = (Local.timestamp_millis(0), Local::now());
+        assert_eq!(
+            db.select_aggregate("test", Aggregate::Mean, &since, &until).await?,
+            Some(21.0)
+        );
+        assert_eq!(
+            db.select_aggregate("test", Aggregate::Min, &since, &until).await?,
+            Some(20.0)
+        );
+        assert_eq!(
+            db.select_aggregate("test", Aggregate::Max, &since, &until).await?,
+            Some(22.0)
+        );
+        assert_eq!(
+            db.select_aggregate("missing", Aggregate::Mean, &since, &until).await?,
+            None
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_consumption_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        for (timestamp, value) in &[(1_000, 10), (2_000, 15), (3_000, 3), (4_000, 5)] {
+            let message = Message::new("test")
+                .value(Value::Counter(*value))
+                .timestamp(Local.timestamp_millis(*timestamp));
+            db.upsert_message(&message).await?;
+        }
+        let statistics = db
+            .select_statistics("test", &Local.timestamp_millis(0), &Local::now())
+            .await?
+            .unwrap();
+        assert_eq!(statistics.consumption, Some(10.0));
+        assert_eq!(statistics.median, 7.5);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_last_reading_before_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test").value(Value::Boolean(true));
+        for timestamp in &[1_566_424_127_000, 1_566_424_128_000, 1_566_424_129_000] {
+            db.upsert_message(&message.clone().timestamp(Local.timestamp_millis(*timestamp)))
+                .await?;
+        }
+        let reading = db
+            .select_last_reading_before("test", &Local.timestamp_millis(1_566_424_129_000))
+            .await?;
+        assert_eq!(reading.unwrap().timestamp, Local.timestamp_millis(1_566_424_128_000));
+        assert_eq!(
+            db.select_last_reading_before("test", &Local.timestamp_millis(1_566_424_127_000))
+                .await?,
+            None
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_reading_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test").value(Value::Counter(42));
+        for timestamp in &[1_566_424_127_000, 1_566_424_128_000] {
+            db.upsert_message(&message.clone().timestamp(Local.timestamp_millis(*timestamp)))
+                .await?;
+        }
+        let reading = db
+            .select_reading("test", &Local.timestamp_millis(1_566_424_127_000))
+            .await?;
+        assert_eq!(reading.unwrap().timestamp, Local.timestamp_millis(1_566_424_127_000));
+        assert_eq!(
+            db.select_reading("test", &Local.timestamp_millis(1_566_424_127_001))
+                .await?,
+            None
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_media_readings_between_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let content = Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]);
+        for (timestamp, value) in vec![
+            (1_000, Value::ImageUrl("https://example.com/1.jpg".into())),
+            (2_000, Value::Text("skipped".into())),
+            (3_000, Value::Blob(Arc::new(content.clone()))),
+        ] {
+            let message = Message::new("test")
+                .value(value)
+                .timestamp(Local.timestamp_millis(timestamp));
+            db.upsert_message(&message).await?;
+        }
+        let readings = db
+            .select_media_readings_between("test", &Local.timestamp_millis(0), &Local::now())
+            .await?;
+        assert_eq!(
+            readings,
+            vec![
+                MediaReading {
+                    timestamp: Local.timestamp_millis(3_000),
+                    value: MediaValue::Blob { head: content, size: 5 },
+                },
+                MediaReading {
+                    timestamp: Local.timestamp_millis(1_000),
+                    value: MediaValue::ImageUrl("https://example.com/1.jpg".into()),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn select_distinct_values_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test").value(Value::StringEnum("on".into()));
+        db.upsert_message(&message.clone().timestamp(Local.timestamp_millis(1_566_424_127_000)))
+            .await?;
+        db.upsert_message(&message.timestamp(Local.timestamp_millis(1_566_424_128_000)))
+            .await?;
+        assert_eq!(
+            db.select_distinct_values("test", 10).await?,
+            vec![Value::StringEnum("on".into())],
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn delete_sensor_deletes_readings() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test")
+            .value(Value::Counter(42))
+            .timestamp(Local.timestamp_millis(1_566_424_128_000));
+        db.upsert_message(&message).await?;
+        db.delete_sensor("test").await?;
+        assert_eq!(db.select_sensor_count().await?, 0);
+        assert_eq!(db.select_total_reading_count().await?, 0);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn hidden_sensor_is_excluded_from_actuals() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test")
+            .value(Value::Counter(42))
+            .timestamp(Local.timestamp_millis(1_566_424_128_000));
+        db.upsert_message(&message).await?;
+        db.set_sensors_hidden(&["test"], true).await?;
+        assert_eq!(db.select_actuals().await?, vec![]);
+
+        // Upserting a new reading must keep the sensor hidden.
+        db.upsert_message(&message.timestamp(Local.timestamp_millis(1_566_424_129_000)))
+            .await?;
+        let summaries = db.select_sensor_summaries().await?;
+        assert_eq!(summaries.len(), 1);
+        assert!(summaries[0].is_hidden);
+        assert_eq!(summaries[0].reading_count, 2);
+
+        let summary = db.select_sensor_summary("test").await?.unwrap();
+        assert_eq!(summary, summaries[0]);
+        assert_eq!(db.select_sensor_summary("missing").await?, None);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn delete_readings_older_than_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let message = Message::new("test").value(Value::Counter(42));
+        db.upsert_message(&message.clone().timestamp(Local.timestamp_millis(1_566_424_127_000)))
+            .await?;
+        db.upsert_message(&message.timestamp(Local.timestamp_millis(1_566_424_129_000)))
+            .await?;
+        let deleted_count = db
+            .delete_readings_older_than(&["test"], &Local.timestamp_millis(1_566_424_128_000))
+            .await?;
+        assert_eq!(deleted_count, 1);
+        assert_eq!(db.select_total_reading_count().await?, 1);
+        Ok(())
+    }
+
     #[async_std::test]
     async fn get_set_user_data_ok() -> Result {
         let db = Connection::open(":memory:").await?;
@@ -415,10 +1401,78 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn delete_user_data_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        db.set_user_data("hello::world", 42_i32, None).await?;
+        db.delete_user_data("hello::world").await?;
+        assert_eq!(db.get_user_data::<i32>("hello::world").await?, None);
+        Ok(())
+    }
+
     #[async_std::test]
     async fn missing_user_data_returns_none() -> Result {
         let db = Connection::open(":memory:").await?;
         assert_eq!(db.get_user_data::<String>("hello::world").await?, None);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn annotations_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let mut battery = Annotation::new("Replaced the battery", Local.timestamp_millis(2000));
+        battery.sensor_id = Some("thermostat".into());
+        let mut holiday = Annotation::new("Holiday", Local.timestamp_millis(1000));
+        holiday.until = Local.timestamp_millis(5000);
+        holiday.location = Some("Home".into());
+        let mut other = Annotation::new("Other sensor", Local.timestamp_millis(2000));
+        other.sensor_id = Some("other".into());
+        battery.id = db.insert_annotation(&battery).await?;
+        holiday.id = db.insert_annotation(&holiday).await?;
+        db.insert_annotation(&other).await?;
+
+        let annotations = db
+            .select_annotations(
+                "thermostat",
+                "Home",
+                &Local.timestamp_millis(1500),
+                &Local.timestamp_millis(3000),
+            )
+            .await?;
+        assert_eq!(annotations, vec![holiday.clone(), battery.clone()]);
+        let annotations = db
+            .select_annotations(
+                "thermostat",
+                "Home",
+                &Local.timestamp_millis(3000),
+                &Local.timestamp_millis(4000),
+            )
+            .await?;
+        assert_eq!(annotations, vec![holiday.clone()]);
+
+        assert!(!db.delete_sensor_annotation(battery.id, "other", "Home").await?);
+        assert!(db.delete_sensor_annotation(battery.id, "thermostat", "Home").await?);
+        assert!(db.delete_annotation(holiday.id).await?);
+        assert!(!db.delete_annotation(holiday.id).await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn push_subscriptions_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let subscription = PushSubscription {
+            endpoint: "https://push.example.com/1".into(),
+            keys: PushSubscriptionKeys {
+                p256dh: "key".into(),
+                auth: "secret".into(),
+            },
+        };
+        db.upsert_push_subscription("push", &subscription).await?;
+        db.upsert_push_subscription("push", &subscription).await?;
+        assert_eq!(db.select_push_subscriptions("push").await?, vec![subscription.clone()]);
+        assert_eq!(db.select_push_subscriptions("other").await?, vec![]);
+        db.delete_push_subscription(&subscription.endpoint).await?;
+        assert_eq!(db.select_push_subscriptions("push").await?, vec![]);
+        Ok(())
+    }
 }
@@ -0,0 +1,38 @@
+use crate::prelude::*;
+
+/// Free text note on a time range, for example: «replaced the thermostat battery» or «holiday».
+///
+/// Attached to either a sensor or a location, or to everything if neither is set.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Annotation {
+    /// Assigned by the database.
+    pub id: i64,
+
+    pub sensor_id: Option<String>,
+    pub location: Option<String>,
+    pub text: String,
+    pub author: Option<String>,
+    pub since: DateTime<Local>,
+
+    /// Inclusive range end, equals to `since` for a point in time.
+    pub until: DateTime<Local>,
+}
+
+impl Annotation {
+    /// Creates the annotation of the point in time.
+    pub fn new<S: Into<String>>(text: S, timestamp: DateTime<Local>) -> Self {
+        Self {
+            id: 0,
+            sensor_id: None,
+            location: None,
+            text: text.into(),
+            author: None,
+            since: timestamp,
+            until: timestamp,
+        }
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.since == self.until
+    }
+}
@@ -0,0 +1,19 @@
+use crate::prelude::*;
+
+/// Image or binary reading, which is selected without loading the blob.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MediaReading {
+    pub timestamp: DateTime<Local>,
+    pub value: MediaValue,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum MediaValue {
+    ImageUrl(String),
+
+    /// The first bytes of the blob, which are enough to sniff its MIME type, and the blob size.
+    Blob {
+        head: Bytes,
+        size: usize,
+    },
+}
@@ -1,4 +1,7 @@
-pub const MIGRATIONS: &[&str] = &[V1, V2, V3, V4];
+pub const MIGRATIONS: &[&str] = &[V1, V2, V3, V4, V5, V6, V7, V8];
+
+/// The migration which adds the numeric values, the existing readings are filled in by `Connection`.
+pub const NUMERIC_VALUE_VERSION: i32 = 8;
 
 // language=sql
 const V1: &str = r#"
@@ -83,3 +86,49 @@ const V4: &str = r#"
 
     PRAGMA user_version = 4;
 "#;
+
+// language=sql
+const V5: &str = r#"
+    ALTER TABLE sensors ADD COLUMN is_hidden INTEGER NOT NULL DEFAULT 0;
+    PRAGMA user_version = 5;
+"#;
+
+// language=sql
+const V6: &str = r#"
+    CREATE TABLE push_subscriptions (
+        endpoint TEXT NOT NULL PRIMARY KEY,
+        service_id TEXT NOT NULL,
+        p256dh TEXT NOT NULL, -- client public key, base64-encoded
+        auth TEXT NOT NULL, -- authentication secret, base64-encoded
+        created_at INTEGER NOT NULL -- unix time, milliseconds
+    );
+
+    CREATE INDEX push_subscriptions_service_id ON push_subscriptions (service_id);
+
+    PRAGMA user_version = 6;
+"#;
+
+// language=sql
+const V7: &str = r#"
+    CREATE TABLE annotations (
+        pk INTEGER NOT NULL PRIMARY KEY,
+        sensor_id TEXT NULL, -- `NULL` unless the annotation is attached to the sensor
+        location TEXT NULL, -- `NULL` unless the annotation is attached to the location
+        text TEXT NOT NULL,
+        author TEXT NULL,
+        since INTEGER NOT NULL, -- unix time, milliseconds
+        until INTEGER NOT NULL, -- unix time, milliseconds, inclusive
+        created_at INTEGER NOT NULL -- unix time, milliseconds
+    );
+
+    CREATE INDEX annotations_since ON annotations (since);
+
+    PRAGMA user_version = 7;
+"#;
+
+// language=sql
+const V8: &str = r#"
+    ALTER TABLE readings ADD COLUMN numeric_value REAL NULL; -- `NULL` unless the value is a finite number
+
+    PRAGMA user_version = 8;
+"#;
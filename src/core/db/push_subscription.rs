@@ -0,0 +1,20 @@
+use crate::prelude::*;
+
+/// Browser [push subscription](https://developer.mozilla.org/en-US/docs/Web/API/PushSubscription),
+/// as serialized by `PushSubscription.toJSON()`.
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
+pub struct PushSubscription {
+    /// Push service URL to deliver the notifications to.
+    pub endpoint: String,
+
+    pub keys: PushSubscriptionKeys,
+}
+
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
+pub struct PushSubscriptionKeys {
+    /// Client P-256 public key, used to encrypt the payload.
+    pub p256dh: String,
+
+    /// Authentication secret.
+    pub auth: String,
+}
@@ -0,0 +1,20 @@
+use crate::prelude::*;
+
+/// Selects the sensor readings within the half-open `[since, until)` range, optionally bounded by the value.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ReadingQuery {
+    pub since: DateTime<Local>,
+    pub until: DateTime<Local>,
+
+    /// Inclusive lower bound of the numeric value.
+    /// Non-numeric readings are filtered out as soon as any bound is set.
+    pub min: Option<f64>,
+
+    /// Inclusive upper bound of the numeric value.
+    pub max: Option<f64>,
+
+    /// Sorts by the value instead of the timestamp.
+    pub by_value: bool,
+
+    pub descending: bool,
+}
@@ -0,0 +1,19 @@
+use crate::prelude::*;
+
+/// Sensor with its storage statistics, used to manage the sensors.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SensorSummary {
+    pub sensor: Sensor,
+
+    /// The latest reading.
+    pub reading: Reading,
+
+    /// Tells whether the sensor is hidden from the dashboard.
+    pub is_hidden: bool,
+
+    pub reading_count: i64,
+
+    /// Total size of the stored reading values in bytes.
+    /// It doesn't account for the row and index overhead, so it's only an estimate.
+    pub reading_size: i64,
+}
@@ -0,0 +1,84 @@
+use crate::prelude::*;
+
+/// Descriptive statistics of the numeric sensor readings within a period.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Statistics {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+
+    /// The earliest reading within the period.
+    pub first: Reading,
+
+    /// The latest reading within the period.
+    pub last: Reading,
+
+    /// Difference between the last and the first values.
+    pub delta: f64,
+
+    /// Average change per hour, `None` if all the readings have the same timestamp.
+    pub rate_per_hour: Option<f64>,
+
+    /// Sum of the increments for the cumulative values like counters and meters, `None` otherwise.
+    ///
+    /// Decrements are treated as the counter resets.
+    pub consumption: Option<f64>,
+}
+
+/// Single aggregate of the numeric sensor readings, which is cheaper to calculate than all the statistics.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aggregate {
+    Mean,
+    Min,
+    Max,
+}
+
+/// Returns the value which the statistics are calculated of, `None` for non-numeric and non-finite values.
+pub fn numeric_value(value: &Value) -> Option<f64> {
+    value.to_f64().filter(|value| value.is_finite())
+}
+
+/// Tells whether the value is a monotonically increasing meter reading.
+pub fn is_cumulative(value: &Value) -> bool {
+    matches!(value, Value::Counter(_) | Value::Energy(_) | Value::Volume(_))
+}
+
+/// Returns the average change per hour, `None` if the readings have the same timestamp.
+pub fn rate_per_hour(first: &Reading, last: &Reading, delta: f64) -> Option<f64> {
+    let hours = (last.timestamp - first.timestamp).num_milliseconds() as f64 / 3_600_000.0;
+    if hours > 0.0 {
+        Some(delta / hours)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(timestamp: i64, value: Value) -> Reading {
+        Reading {
+            timestamp: Local.timestamp_millis(timestamp),
+            value,
+        }
+    }
+
+    #[test]
+    fn rate_per_hour_ok() {
+        let first = reading(0, Value::Temperature(2.0));
+        let last = reading(1_800_000, Value::Temperature(4.0));
+        assert_eq!(rate_per_hour(&first, &last, 2.0), Some(4.0));
+        assert_eq!(rate_per_hour(&first, &first, 0.0), None);
+    }
+
+    #[test]
+    fn numeric_value_skips_nan() {
+        assert_eq!(numeric_value(&Value::Temperature(f64::NAN)), None);
+        assert_eq!(numeric_value(&Value::Text("foo".into())), None);
+        assert_eq!(numeric_value(&Value::Counter(42)), Some(42.0));
+    }
+}
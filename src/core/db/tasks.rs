@@ -1,5 +1,6 @@
 //! Database persistence tasks.
 
+use crate::core::metrics;
 use crate::prelude::*;
 
 const COMMIT_INTERVAL_MIN: Duration = Duration::from_millis(50);
@@ -18,6 +19,7 @@ pub fn spawn(db: Connection, bus: &mut Bus) {
 fn spawn_committer(db: Connection, buffer: Arc<Mutex<Vec<Message>>>) {
     task::spawn(async move {
         let mut commit_interval = Duration::from_millis(1000);
+        metrics::record_commit_interval(commit_interval);
 
         loop {
             task::sleep(commit_interval).await;
@@ -33,11 +35,13 @@ fn spawn_committer(db: Connection, buffer: Arc<Mutex<Vec<Message>>>) {
             };
 
             if !messages.is_empty() {
-                info!("Upserting a bulk of {} messages…", messages.len());
+                let message_count = messages.len();
+                info!("Upserting a bulk of {} messages…", message_count);
                 let start_time = Instant::now();
                 let _ = db.upsert_messages(messages).await.log(|| "failed to upsert");
                 let elapsed = start_time.elapsed();
                 info!("Upserted in {:.1?}.", elapsed);
+                metrics::record_commit(message_count, elapsed);
 
                 if elapsed > commit_interval {
                     commit_interval = COMMIT_INTERVAL_MAX.min(commit_interval * 2);
@@ -45,6 +49,7 @@ fn spawn_committer(db: Connection, buffer: Arc<Mutex<Vec<Message>>>) {
                     commit_interval = COMMIT_INTERVAL_MIN.max(commit_interval / 2);
                 }
                 info!("Commit interval: {:?}.", commit_interval);
+                metrics::record_commit_interval(commit_interval);
             }
         }
     });
@@ -0,0 +1,63 @@
+//! Process-wide metrics, exposed via the `/metrics` endpoint.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::prelude::*;
+
+/// Total number of the messages dispatched by the bus.
+static BUS_MESSAGES: AtomicU64 = AtomicU64::new(0);
+
+/// Total number of the database commits.
+static COMMITS: AtomicU64 = AtomicU64::new(0);
+
+/// Total number of the committed messages.
+static COMMITTED_MESSAGES: AtomicU64 = AtomicU64::new(0);
+
+/// Total time spent in the commits, in microseconds.
+static COMMIT_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// The latest commit duration, in microseconds.
+static LAST_COMMIT_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Current commit interval, in milliseconds.
+static COMMIT_INTERVAL_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+/// Point-in-time copy of the process metrics.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub bus_messages: u64,
+    pub commits: u64,
+    pub committed_messages: u64,
+    pub commit_duration: Duration,
+    pub last_commit_duration: Duration,
+    pub commit_interval: Duration,
+}
+
+/// Counts the message dispatched by the bus.
+pub fn record_bus_message() {
+    BUS_MESSAGES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Records the database commit of the messages.
+pub fn record_commit(message_count: usize, elapsed: Duration) {
+    let micros = elapsed.as_micros() as u64;
+    COMMITS.fetch_add(1, Ordering::Relaxed);
+    COMMITTED_MESSAGES.fetch_add(message_count as u64, Ordering::Relaxed);
+    COMMIT_MICROS.fetch_add(micros, Ordering::Relaxed);
+    LAST_COMMIT_MICROS.store(micros, Ordering::Relaxed);
+}
+
+pub fn record_commit_interval(interval: Duration) {
+    COMMIT_INTERVAL_MILLIS.store(interval.as_millis() as u64, Ordering::Relaxed);
+}
+
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        bus_messages: BUS_MESSAGES.load(Ordering::Relaxed),
+        commits: COMMITS.load(Ordering::Relaxed),
+        committed_messages: COMMITTED_MESSAGES.load(Ordering::Relaxed),
+        commit_duration: Duration::from_micros(COMMIT_MICROS.load(Ordering::Relaxed)),
+        last_commit_duration: Duration::from_micros(LAST_COMMIT_MICROS.load(Ordering::Relaxed)),
+        commit_interval: Duration::from_millis(COMMIT_INTERVAL_MILLIS.load(Ordering::Relaxed)),
+    }
+}
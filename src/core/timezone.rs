@@ -0,0 +1,94 @@
+//! Process-wide time zone.
+//!
+//! Timestamps are stored as UTC milliseconds and handled as `DateTime<Local>`. The configured time zone,
+//! or the host one if it's not configured, defines how they're displayed and how the calendar dates
+//! and times are resolved.
+
+use std::sync::RwLock;
+
+use chrono::{FixedOffset, Offset};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+
+use crate::prelude::*;
+
+lazy_static! {
+    static ref TIMEZONE: RwLock<Option<Tz>> = RwLock::new(None);
+}
+
+/// Sets the time zone, `None` stands for the host one.
+pub fn set(timezone: Option<Tz>) {
+    *TIMEZONE.write().unwrap() = timezone;
+}
+
+pub fn get() -> Option<Tz> {
+    *TIMEZONE.read().unwrap()
+}
+
+/// Returns the [tz database](https://en.wikipedia.org/wiki/Tz_database) name, if the time zone is configured.
+pub fn name() -> Option<&'static str> {
+    get().map(|timezone| timezone.name())
+}
+
+/// Converts the timestamp to the time zone.
+pub fn localize(timestamp: &DateTime<Local>) -> DateTime<FixedOffset> {
+    localize_in(get(), timestamp)
+}
+
+/// Returns the current time in the time zone.
+pub fn now() -> DateTime<FixedOffset> {
+    localize(&Local::now())
+}
+
+/// Resolves the date and time in the time zone, the earliest one is picked if it's ambiguous.
+pub fn from_local(datetime: &NaiveDateTime) -> Option<DateTime<Local>> {
+    from_local_in(get(), datetime)
+}
+
+fn localize_in(timezone: Option<Tz>, timestamp: &DateTime<Local>) -> DateTime<FixedOffset> {
+    match timezone {
+        Some(timezone) => {
+            let timestamp = timestamp.with_timezone(&timezone);
+            timestamp.with_timezone(&timestamp.offset().fix())
+        }
+        None => timestamp.with_timezone(&timestamp.offset().fix()),
+    }
+}
+
+fn from_local_in(timezone: Option<Tz>, datetime: &NaiveDateTime) -> Option<DateTime<Local>> {
+    match timezone {
+        Some(timezone) => timezone
+            .from_local_datetime(datetime)
+            .earliest()
+            .map(|datetime| datetime.with_timezone(&Local)),
+        None => Local.from_local_datetime(datetime).earliest(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Asia::Tokyo;
+    use chrono_tz::Europe::Amsterdam;
+
+    use super::*;
+
+    #[test]
+    fn localize_ok() {
+        let timestamp = Local.timestamp_millis(0);
+        assert_eq!(localize_in(Some(Tokyo), &timestamp).hour(), 9);
+        assert_eq!(localize_in(None, &timestamp), timestamp);
+    }
+
+    #[test]
+    fn from_local_ok() {
+        let datetime = NaiveDate::from_ymd(2020, 7, 1).and_hms(12, 0, 0);
+        let timestamp = from_local_in(Some(Amsterdam), &datetime).unwrap();
+        assert_eq!(timestamp, Utc.ymd(2020, 7, 1).and_hms(10, 0, 0));
+    }
+
+    #[test]
+    fn non_existent_local_time_none() {
+        let datetime = NaiveDate::from_ymd(2020, 3, 29).and_hms(2, 30, 0);
+        assert_eq!(from_local_in(Some(Amsterdam), &datetime), None);
+    }
+}
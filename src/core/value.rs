@@ -81,6 +81,42 @@ pub enum Value {
     Other,
 }
 
+impl Value {
+    /// Returns the value variant name, for example: `Temperature`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::None => "None",
+            Value::Counter(_) => "Counter",
+            Value::ImageUrl(_) => "ImageUrl",
+            Value::Boolean(_) => "Boolean",
+            Value::DataSize(_) => "DataSize",
+            Value::Text(_) => "Text",
+            Value::Bft(_) => "Bft",
+            Value::Rh(_) => "Rh",
+            Value::Temperature(_) => "Temperature",
+            Value::Length(_) => "Length",
+            Value::Duration(_) => "Duration",
+            Value::RelativeIntensity(_) => "RelativeIntensity",
+            Value::Power(_) => "Power",
+            Value::Volume(_) => "Volume",
+            Value::Energy(_) => "Energy",
+            Value::Speed(_) => "Speed",
+            Value::Cloudiness(_) => "Cloudiness",
+            Value::BatteryLife(_) => "BatteryLife",
+            Value::Blob(_) => "Blob",
+            Value::StringEnum(_) => "StringEnum",
+            Value::Other => "Other",
+        }
+    }
+
+    /// Returns the numeric value converting integers to floats, or `None` for the non-numeric values.
+    pub fn to_f64(&self) -> Option<f64> {
+        f64::try_from(self)
+            .ok()
+            .or_else(|| i64::try_from(self).ok().map(|value| value as f64))
+    }
+}
+
 impl AsRef<Value> for Value {
     fn as_ref(&self) -> &Self {
         &self
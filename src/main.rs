@@ -24,14 +24,21 @@ async fn main() -> Result {
         println!("{}", crate_version!());
         return Ok(());
     }
+    if opts.hash_password {
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password)?;
+        println!("{}", web::hash_password(password.trim_end_matches(&['\r', '\n'][..]))?);
+        return Ok(());
+    }
 
     logging::init(&opts)?;
 
     info!("Reading the settings…");
     let settings = settings::read(opts.settings)?;
     debug!("Settings: {:?}", &settings);
+    core::timezone::set(settings.timezone);
 
-    let _sentry_guard = settings.secrets.sentry_dsn.as_ref().map(crate::sentry::init);
+    let _sentry_guard = settings.secrets.sentry_dsn.as_deref().map(crate::sentry::init);
 
     info!("Opening the database…");
     let db = Connection::open(&settings.database.path).await?;
@@ -43,7 +50,12 @@ async fn main() -> Result {
     services::spawn_all(&settings, &mut bus, &db).await?;
 
     if !settings.http.disabled {
-        std::thread::spawn(move || web::start_server(&settings, db));
+        if settings.http.users.is_empty() {
+            warn!("No users are defined, the web interface is accessible to anyone.");
+        }
+        let tx = bus.add_tx();
+        let rx = bus.add_rx();
+        std::thread::spawn(move || web::start_server(&settings, db, tx, rx));
     } else {
         warn!("Web server is disabled.");
     }
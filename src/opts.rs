@@ -24,4 +24,8 @@ pub struct Opts {
     /// Prints version information
     #[structopt(short = "V", long = "version")]
     pub version: bool,
+
+    /// Reads a password from the standard input and prints its hash for the user settings
+    #[structopt(long = "hash-password")]
+    pub hash_password: bool,
 }
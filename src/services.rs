@@ -15,6 +15,7 @@ pub mod solar;
 pub mod tado;
 pub mod telegram;
 pub mod threshold;
+pub mod web_push;
 pub mod youless;
 
 /// Spawn all the configured services.
@@ -29,13 +30,14 @@ pub async fn spawn_all(settings: &Settings, bus: &mut Bus, db: &Connection) -> R
                 Service::Clock(service) => service.spawn(service_id, bus).await,
                 Service::OpenWeather(service) => service.spawn(service_id, bus),
                 Service::PhilipsHue(service) => service.spawn(service_id, bus),
-                Service::Rhai(service) => service.spawn(service_id, bus, settings.services.clone()),
+                Service::Rhai(service) => service.spawn(service_id, bus, db, settings.services.clone()),
                 Service::Ring(service) => service.spawn(service_id, db.clone(), bus),
                 Service::SimpleAnomalyDetector(service) => service.spawn(service_id, bus, db).await,
                 Service::Solar(service) => service.spawn(service_id, bus),
                 Service::Tado(service) => service.spawn(service_id, bus).await,
                 Service::Telegram(service) => service.spawn(service_id, bus),
                 Service::Threshold(service) => service.spawn(service_id, bus),
+                Service::WebPush(service) => service.spawn(service_id, bus, db),
                 Service::YouLess(service) => service.spawn(service_id, bus),
             }
         } {
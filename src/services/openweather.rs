@@ -10,9 +10,9 @@ pub struct OpenWeather {
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 struct Secrets {
-    api_key: String,
-    latitude: f64,
-    longitude: f64,
+    api_key: Secret,
+    latitude: Secret<f64>,
+    longitude: Secret<f64>,
 }
 
 /// <https://openweathermap.org/current>
@@ -34,7 +34,7 @@ impl OpenWeather {
                 &[
                     ("units", "metric"),
                     ("lang", "en"),
-                    ("appid", &self.secrets.api_key),
+                    ("appid", self.secrets.api_key.as_str()),
                     ("lat", &self.secrets.latitude.to_string()),
                     ("lon", &self.secrets.longitude.to_string()),
                 ],
@@ -5,5 +5,6 @@ pub use crate::services::helpers::deserialize_timestamp;
 pub use crate::services::helpers::expect::expect;
 pub use crate::services::helpers::handle_result::handle_service_result;
 pub use crate::services::helpers::middleware::inject_default_headers;
+pub use crate::settings::Secret;
 
 pub const MINUTE: Duration = Duration::from_secs(60);
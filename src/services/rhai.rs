@@ -1,16 +1,30 @@
+use std::fmt::Write;
 use std::process::Command;
 
+use async_std::future;
 use bytes::Bytes;
 use itertools::Itertools;
 use regex::Regex;
-use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, RegisterFn, RegisterResultFn, Scope};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, RegisterFn, RegisterResultFn, Scope, AST};
 
+use crate::core::db::annotation::Annotation;
+use crate::core::timezone;
 use crate::prelude::*;
 use crate::settings::Service;
 
 type FnResult = StdResult<Dynamic, Box<EvalAltResult>>;
 
+mod http;
+mod json;
+mod queries;
+mod schedule;
+mod state;
 mod telegram;
+mod timers;
+mod web_push;
+
+/// Maximum time to wait for a message if there're no timers.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(3600);
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Rhai {
@@ -21,37 +35,76 @@ pub struct Rhai {
 }
 
 impl Rhai {
-    pub fn spawn(self, service_id: String, bus: &mut Bus, services: HashMap<String, Service>) -> Result {
+    pub fn spawn(
+        self,
+        service_id: String,
+        bus: &mut Bus,
+        db: &Connection,
+        services: HashMap<String, Service>,
+    ) -> Result {
         let mut rx = bus.add_rx();
 
         let mut engine = Engine::new();
         engine.set_max_expr_depths(128, 32);
         let mut ast = engine.compile(&self.script)?;
         let mut scope = Scope::new();
+        let timers = timers::Timers::new(service_id.clone(), db.clone());
 
         Self::register_global_functions(&service_id, &mut engine);
         Self::register_functions(&mut engine, bus.add_tx());
+        Self::register_annotation_functions(&service_id, &mut engine, db);
+        queries::register_functions(&mut engine, db);
+        timers::register_functions(&mut engine, &timers);
         Self::push_constants(&mut scope);
-        Self::push_services(&mut scope, services);
+        scope.push_constant("state", state::State::new(service_id.clone(), db.clone()));
+        Self::push_services(&mut scope, services, db);
 
         engine.consume_ast_with_scope(&mut scope, &ast)?;
 
         task::spawn(async move {
-            while let Some(message) = rx.next().await {
+            let _ = timers
+                .restore()
+                .await
+                .log(|| format!("[{}] failed to restore the timers", service_id));
+
+            loop {
+                let callbacks = timers
+                    .take_due()
+                    .await
+                    .log(|| format!("[{}] failed to update the timers", service_id))
+                    .unwrap_or_default();
+                for callback in callbacks.into_iter() {
+                    let call = call_fn(service_id.clone(), engine, ast, scope, callback, None);
+                    let (engine_, ast_, scope_) = call.await;
+                    engine = engine_;
+                    ast = ast_;
+                    scope = scope_;
+                }
+
+                let timeout = timers.next_run().map_or(IDLE_TIMEOUT, |next_run| {
+                    (next_run - Local::now()).to_std().unwrap_or_default()
+                });
+                let message = match future::timeout(timeout, rx.next()).await {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    // A timer is due.
+                    Err(_) => continue,
+                };
                 if let Some(pattern) = &self.sensor_pattern {
                     if !pattern.is_match(&message.sensor.id) {
                         debug!("[{}] `{}` is filtered out.", service_id, message.sensor.id);
                         continue;
                     }
                 }
-                let service_id = service_id.clone();
-                let (engine_, ast_, scope_) = task::spawn(async move {
-                    if let Err(error) = engine.call_fn::<_, Dynamic>(&mut scope, &ast, "on_message", (message,)) {
-                        error!("[{}] `on_message` has failed: {}", service_id, error);
-                    }
-                    (engine, ast, scope)
-                })
-                .await;
+                let call = call_fn(
+                    service_id.clone(),
+                    engine,
+                    ast,
+                    scope,
+                    "on_message".into(),
+                    Some(message),
+                );
+                let (engine_, ast_, scope_) = call.await;
                 engine = engine_;
                 ast = ast_;
                 scope = scope_;
@@ -70,12 +123,70 @@ impl Rhai {
 
     fn register_functions(engine: &mut Engine, tx: Sender) {
         Self::register_debug_functions::<MessageType>(engine);
-        Self::register_debug_functions::<DateTime<Local>>(engine);
-
+        Self::register_datetime_functions(engine);
         Self::register_message_functions(engine, tx);
         Self::register_value_functions(engine);
 
+        http::register_functions(engine);
+        state::register_functions(engine);
         telegram::register_functions(engine);
+        web_push::register_functions(engine);
+    }
+
+    /// Registers `annotate` and `annotate_location`, the annotations are signed by the service ID.
+    fn register_annotation_functions(service_id: &str, engine: &mut Engine, db: &Connection) {
+        let annotate = {
+            let service_id = service_id.to_string();
+            let db = db.clone();
+            move |mut annotation: Annotation| {
+                annotation.author = Some(service_id.clone());
+                let db = db.clone();
+                let service_id = service_id.clone();
+                task::spawn(async move {
+                    let _ = db
+                        .insert_annotation(&annotation)
+                        .await
+                        .log(|| format!("[{}] failed to annotate", service_id));
+                });
+            }
+        };
+        {
+            let annotate = annotate.clone();
+            engine.register_fn("annotate", move |sensor_id: &str, text: &str| {
+                let mut annotation = Annotation::new(text, Local::now());
+                annotation.sensor_id = Some(sensor_id.into());
+                annotate(annotation);
+            });
+        }
+        {
+            let annotate = annotate.clone();
+            engine.register_fn(
+                "annotate",
+                move |sensor_id: &str, text: &str, since: DateTime<Local>, until: DateTime<Local>| {
+                    let mut annotation = Annotation::new(text, since);
+                    annotation.sensor_id = Some(sensor_id.into());
+                    annotation.until = until;
+                    annotate(annotation);
+                },
+            );
+        }
+        {
+            let annotate = annotate.clone();
+            engine.register_fn("annotate_location", move |location: &str, text: &str| {
+                let mut annotation = Annotation::new(text, Local::now());
+                annotation.location = Some(location.into());
+                annotate(annotation);
+            });
+        }
+        engine.register_fn(
+            "annotate_location",
+            move |location: &str, text: &str, since: DateTime<Local>, until: DateTime<Local>| {
+                let mut annotation = Annotation::new(text, since);
+                annotation.location = Some(location.into());
+                annotation.until = until;
+                annotate(annotation);
+            },
+        );
     }
 
     fn push_constants(scope: &mut Scope) {
@@ -85,13 +196,16 @@ impl Rhai {
     }
 
     /// Assigns the service instances to the inner variables.
-    fn push_services(scope: &mut Scope, services: HashMap<String, Service>) {
+    fn push_services(scope: &mut Scope, services: HashMap<String, Service>, db: &Connection) {
         for (service_id, service) in services.into_iter() {
-            #[allow(clippy::single_match)]
             match service {
                 Service::Telegram(telegram) => {
                     scope.push_constant(service_id, telegram);
                 }
+                Service::WebPush(service) => {
+                    let notifier = service.into_notifier(service_id.clone(), db.clone());
+                    scope.push_constant(service_id, notifier);
+                }
                 _ => (),
             }
         }
@@ -131,6 +245,42 @@ impl Rhai {
         engine.register_fn("+", |left: T, right: &str| format!("{:?}", left) + right);
     }
 
+    /// Registers `now()` and the date and time getters, which are calculated in the configured time zone.
+    fn register_datetime_functions(engine: &mut Engine) {
+        Self::register_debug_functions::<DateTime<Local>>(engine);
+
+        engine.register_fn("now", Local::now);
+        engine.register_get("year", |this: &mut DateTime<Local>| {
+            timezone::localize(this).year() as i64
+        });
+        engine.register_get("month", |this: &mut DateTime<Local>| {
+            timezone::localize(this).month() as i64
+        });
+        engine.register_get("day", |this: &mut DateTime<Local>| {
+            timezone::localize(this).day() as i64
+        });
+        engine.register_get("hour", |this: &mut DateTime<Local>| {
+            timezone::localize(this).hour() as i64
+        });
+        engine.register_get("minute", |this: &mut DateTime<Local>| {
+            timezone::localize(this).minute() as i64
+        });
+        engine.register_get("second", |this: &mut DateTime<Local>| {
+            timezone::localize(this).second() as i64
+        });
+        engine.register_get("weekday", |this: &mut DateTime<Local>| {
+            timezone::localize(this).weekday().number_from_monday() as i64
+        });
+        engine.register_get("timestamp_millis", |this: &mut DateTime<Local>| this.timestamp_millis());
+        engine.register_result_fn("format", |this: &mut DateTime<Local>, format: &str| -> FnResult {
+            // Chrono panics on an invalid specifier when it's displayed via `to_string()`.
+            let mut formatted = String::new();
+            write!(formatted, "{}", timezone::localize(this).format(format))
+                .map_err(|_| format!("invalid format: `{}`", format))?;
+            Ok(formatted.into())
+        });
+    }
+
     /// Registers `Message` functions.
     fn register_message_functions(engine: &mut Engine, tx: Sender) {
         Self::register_debug_functions::<Message>(engine);
@@ -208,6 +358,32 @@ impl Rhai {
     }
 }
 
+/// Calls the script function on a blocking thread, passing the message if any.
+///
+/// The script functions, like `http_get` or the database queries, block on futures,
+/// so that the call must not occupy an executor thread.
+/// The engine, AST and scope are moved onto the thread and returned back.
+async fn call_fn(
+    service_id: String,
+    mut engine: Engine,
+    ast: AST,
+    mut scope: Scope<'static>,
+    name: String,
+    message: Option<Message>,
+) -> (Engine, AST, Scope<'static>) {
+    task::spawn_blocking(move || {
+        let result = match message {
+            Some(message) => engine.call_fn::<_, Dynamic>(&mut scope, &ast, &name, (message,)),
+            None => engine.call_fn::<_, Dynamic>(&mut scope, &ast, &name, ()),
+        };
+        if let Err(error) = result {
+            error!("[{}] `{}` has failed: {}", service_id, name, error);
+        }
+        (engine, ast, scope)
+    })
+    .await
+}
+
 /// Used to spawn an external process.
 fn spawn_process(program: &str, args: Array) -> FnResult {
     debug!(
@@ -234,6 +410,45 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn annotate_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let mut engine = Engine::new();
+        Rhai::register_annotation_functions("rhai", &mut engine, &db);
+        engine.eval::<()>(r#"annotate("test", "Hello")"#)?;
+
+        let since = Local::now() - chrono::Duration::minutes(1);
+        let until = Local::now() + chrono::Duration::minutes(1);
+        for _ in 0..10 {
+            if let Some(annotation) = db.select_annotations("test", "", &since, &until).await?.pop() {
+                assert_eq!(annotation.text, "Hello");
+                assert_eq!(annotation.author.as_deref(), Some("rhai"));
+                return Ok(());
+            }
+            task::sleep(Duration::from_millis(50)).await;
+        }
+        Err(anyhow!("the annotation is not inserted"))
+    }
+
+    #[test]
+    fn datetime_ok() -> Result {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+        Rhai::register_datetime_functions(&mut engine);
+
+        scope.push("timestamp", Local.ymd(2020, 12, 14).and_hms(21, 30, 15));
+        assert_eq!(engine.eval_with_scope::<i64>(&mut scope, "timestamp.hour")?, 21);
+        assert_eq!(engine.eval_with_scope::<i64>(&mut scope, "timestamp.weekday")?, 1);
+        assert_eq!(
+            engine.eval_with_scope::<String>(&mut scope, r#"timestamp.format("%Y-%m-%d %H:%M")"#)?,
+            "2020-12-14 21:30"
+        );
+        assert!(engine
+            .eval_with_scope::<String>(&mut scope, r#"timestamp.format("%Q")"#)
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_value_inner_ok() -> Result {
         let mut engine = Engine::new();
@@ -0,0 +1,73 @@
+//! HTTP client functions for Rhai.
+//!
+//! The requests go through the shared client, so they follow redirects, time out and fail on error statuses.
+
+use rhai::{Dynamic, Engine, RegisterResultFn};
+use surf::Body;
+
+use crate::prelude::*;
+use crate::services::helpers::client::CLIENT;
+use crate::services::rhai::json::{from_json, to_json};
+use crate::services::rhai::FnResult;
+
+pub fn register_functions(engine: &mut Engine) {
+    engine.register_result_fn("http_get", http_get);
+    engine.register_result_fn("http_get_string", http_get_string);
+    engine.register_result_fn("http_post_json", http_post_json);
+}
+
+/// Requests the URL and parses the response as JSON.
+fn http_get(url: &str) -> FnResult {
+    let body = task::block_on(CLIENT.get(url).recv_string()).map_err(|error| error.to_string())?;
+    parse_body(&body)
+}
+
+/// Requests the URL and returns the response body as is.
+fn http_get_string(url: &str) -> FnResult {
+    let body = task::block_on(CLIENT.get(url).recv_string()).map_err(|error| error.to_string())?;
+    Ok(body.into())
+}
+
+/// Posts the value as JSON and parses the response as JSON.
+fn http_post_json(url: &str, value: Dynamic) -> FnResult {
+    let body = Body::from_json(&to_json(&value)?).map_err(|error| error.to_string())?;
+    let body = task::block_on(CLIENT.post(url).body(body).recv_string()).map_err(|error| error.to_string())?;
+    parse_body(&body)
+}
+
+/// Parses the response body as JSON, an empty body becomes `()`.
+fn parse_body(body: &str) -> FnResult {
+    if body.trim().is_empty() {
+        Ok(().into())
+    } else {
+        Ok(from_json(
+            serde_json::from_str(body).map_err(|error| error.to_string())?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::Map;
+
+    use super::*;
+
+    #[test]
+    fn parse_body_ok() -> Result {
+        let map = parse_body(r#"{"ison": true, "power": 12.5}"#)?.cast::<Map>();
+        assert_eq!(map["ison"].clone().cast::<bool>(), true);
+        assert_eq!(map["power"].clone().cast::<f64>(), 12.5);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_empty_body_ok() -> Result {
+        assert!(parse_body("\n")?.is::<()>());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_body_err() {
+        assert!(parse_body("<html>").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Conversion between Rhai values and JSON.
+
+use rhai::{Array, Dynamic, ImmutableString, Map};
+use serde_json::Value as Json;
+
+use crate::prelude::*;
+
+/// Converts the Rhai value, fails on the values that don't have a JSON representation.
+pub fn to_json(value: &Dynamic) -> StdResult<Json, String> {
+    if value.is::<()>() {
+        Ok(Json::Null)
+    } else if value.is::<bool>() {
+        Ok(Json::Bool(value.clone().cast::<bool>()))
+    } else if value.is::<i64>() {
+        Ok(value.clone().cast::<i64>().into())
+    } else if value.is::<f64>() {
+        Ok(value.clone().cast::<f64>().into())
+    } else if value.is::<char>() {
+        Ok(Json::String(value.clone().cast::<char>().to_string()))
+    } else if value.is::<ImmutableString>() {
+        Ok(Json::String(value.clone().cast::<ImmutableString>().to_string()))
+    } else if value.is::<Array>() {
+        Ok(Json::Array(
+            value
+                .clone()
+                .cast::<Array>()
+                .iter()
+                .map(to_json)
+                .collect::<StdResult<Vec<_>, _>>()?,
+        ))
+    } else if value.is::<Map>() {
+        Ok(Json::Object(
+            value
+                .clone()
+                .cast::<Map>()
+                .iter()
+                .map(|(key, value)| Ok((key.to_string(), to_json(value)?)))
+                .collect::<StdResult<_, String>>()?,
+        ))
+    } else {
+        Err(format!("`{}` cannot be converted to JSON", value.type_name()))
+    }
+}
+
+/// Converts the JSON value, objects become Rhai maps.
+pub fn from_json(value: Json) -> Dynamic {
+    match value {
+        Json::Null => ().into(),
+        Json::Bool(value) => value.into(),
+        Json::Number(number) => match number.as_i64() {
+            Some(number) => number.into(),
+            None => number.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        Json::String(value) => value.into(),
+        Json::Array(values) => values.into_iter().map(from_json).collect::<Vec<Dynamic>>().into(),
+        Json::Object(object) => object
+            .into_iter()
+            .map(|(key, value)| (key, from_json(value)))
+            .collect::<HashMap<String, Dynamic>>()
+            .into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::Engine;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_ok() -> Result {
+        let value = json!({"name": "Shelly", "on": true, "power": 42.5, "channels": [1, 2], "error": null});
+        assert_eq!(
+            to_json(&from_json(value.clone())).map_err(|error| anyhow!(error))?,
+            value
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn map_ok() -> Result {
+        let map = Engine::new().eval::<Dynamic>(r#"#{answer: 42, text: "hello"}"#)?;
+        assert_eq!(
+            to_json(&map).map_err(|error| anyhow!(error))?,
+            json!({"answer": 42, "text": "hello"})
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Database query functions for Rhai: the actual sensor readings, the history and the aggregates.
+
+use rhai::{Array, Dynamic, Engine, RegisterResultFn};
+
+use crate::core::db::statistics::Aggregate;
+use crate::prelude::*;
+use crate::services::rhai::schedule::parse_duration;
+use crate::services::rhai::{FnResult, Rhai};
+
+pub fn register_functions(engine: &mut Engine, db: &Connection) {
+    Rhai::register_debug_functions::<Reading>(engine);
+    engine.register_get("timestamp", |this: &mut Reading| this.timestamp);
+    engine.register_get("value", |this: &mut Reading| this.value.clone());
+
+    {
+        let db = db.clone();
+        engine.register_result_fn("get_sensor", move |sensor_id: &str| {
+            let actual = task::block_on(db.select_sensor(sensor_id)).map_err(|error| error.to_string())?;
+            Ok(actual.map_or_else(|| ().into(), |(_, reading)| Dynamic::from(reading)))
+        });
+    }
+    {
+        let db = db.clone();
+        engine.register_result_fn("get_readings", move |sensor_id: &str, since: DateTime<Local>| {
+            select_readings(&db, sensor_id, &since)
+        });
+    }
+    {
+        let db = db.clone();
+        engine.register_result_fn("get_readings", move |sensor_id: &str, window: &str| {
+            select_readings(&db, sensor_id, &since(window)?)
+        });
+    }
+    register_aggregate(engine, db, "avg", Aggregate::Mean);
+    register_aggregate(engine, db, "min", Aggregate::Min);
+    register_aggregate(engine, db, "max", Aggregate::Max);
+}
+
+/// Registers the aggregate over the numeric readings within the window, which returns `()` if there're none.
+fn register_aggregate(engine: &mut Engine, db: &Connection, name: &str, aggregate: Aggregate) {
+    let db = db.clone();
+    engine.register_result_fn(name, move |sensor_id: &str, window: &str| {
+        let value = task::block_on(db.select_aggregate(sensor_id, aggregate, &since(window)?, &Local::now()))
+            .map_err(|error| error.to_string())?;
+        Ok(value.map_or_else(|| ().into(), Dynamic::from))
+    });
+}
+
+fn select_readings(db: &Connection, sensor_id: &str, since: &DateTime<Local>) -> FnResult {
+    let readings = task::block_on(db.select_readings(sensor_id, since)).map_err(|error| error.to_string())?;
+    Ok(readings.into_iter().map(Dynamic::from).collect::<Array>().into())
+}
+
+/// Returns the start of the window, which ends now.
+fn since(window: &str) -> StdResult<DateTime<Local>, String> {
+    Local::now()
+        .checked_sub_signed(parse_duration(window)?)
+        .ok_or_else(|| format!("the window is out of range: `{}`", window))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn new_engine() -> Result<Engine> {
+        let db = Connection::open(":memory:").await?;
+        let now = Local::now();
+        for (minutes, value) in [(40, 30.0), (20, 20.0), (10, 22.0)].iter() {
+            let message = Message::new("heating::temperature")
+                .value(Value::Temperature(*value))
+                .timestamp(now - chrono::Duration::minutes(*minutes));
+            db.upsert_message(&message).await?;
+        }
+        let mut engine = Engine::new();
+        Rhai::register_value_functions(&mut engine);
+        register_functions(&mut engine, &db);
+        Ok(engine)
+    }
+
+    #[async_std::test]
+    async fn get_sensor_ok() -> Result {
+        let engine = new_engine().await?;
+        assert_eq!(
+            engine.eval::<f64>(r#"get_sensor("heating::temperature").value.inner"#)?,
+            22.0
+        );
+        assert!(engine.eval::<Dynamic>(r#"get_sensor("missing")"#)?.is::<()>());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_readings_ok() -> Result {
+        let engine = new_engine().await?;
+        assert_eq!(
+            engine.eval::<i64>(r#"get_readings("heating::temperature", "30m").len()"#)?,
+            2
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn aggregates_ok() -> Result {
+        let engine = new_engine().await?;
+        assert_eq!(engine.eval::<f64>(r#"avg("heating::temperature", "30m")"#)?, 21.0);
+        assert_eq!(engine.eval::<f64>(r#"min("heating::temperature", "1h")"#)?, 20.0);
+        assert_eq!(engine.eval::<f64>(r#"max("heating::temperature", "1h")"#)?, 30.0);
+        assert!(engine
+            .eval::<Dynamic>(r#"avg("heating::temperature", "5m")"#)?
+            .is::<()>());
+        assert!(engine
+            .eval::<Dynamic>(r#"avg("heating::temperature", "106751991167d")"#)
+            .is_err());
+        Ok(())
+    }
+}
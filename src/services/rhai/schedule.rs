@@ -0,0 +1,265 @@
+//! Timer schedules: intervals, daily times and cron expressions.
+//!
+//! The calendar schedules are resolved in the configured time zone.
+
+use chrono::Duration;
+
+use crate::core::timezone;
+use crate::prelude::*;
+
+/// How often the timer fires.
+#[derive(Clone, Debug)]
+pub enum Schedule {
+    /// Fixed interval.
+    Every(Duration),
+
+    /// Every day at the time.
+    At(NaiveTime),
+
+    Cron(Box<CronExpression>),
+
+    /// One-shot timer, which doesn't fire again.
+    Once,
+}
+
+impl Schedule {
+    /// Returns the next run after the timestamp, `None` means the timer should not fire again.
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Schedule::Every(interval) => after.checked_add_signed(*interval),
+            Schedule::At(time) => {
+                let mut date = timezone::localize(after).naive_local().date();
+                // The time may not exist on a day because of the DST change.
+                for _ in 0..7 {
+                    match timezone::from_local(&date.and_time(*time)) {
+                        Some(timestamp) if timestamp > *after => return Some(timestamp),
+                        _ => date = date.succ(),
+                    }
+                }
+                None
+            }
+            Schedule::Cron(expression) => expression.next_after(after),
+            Schedule::Once => None,
+        }
+    }
+}
+
+/// Parses the duration like `90s`, `5m`, `1h30m` or `1d`. Supported units are `ms`, `s`, `m`, `h` and `d`.
+pub fn parse_duration(value: &str) -> StdResult<Duration, String> {
+    let error = || format!("invalid duration: `{}`", value);
+    let mut duration = Duration::zero();
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(error());
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+        let number: i64 = rest[..digits].parse().map_err(|_| error())?;
+        rest = &rest[digits..];
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or_else(|| rest.len());
+        let unit_millis = match &rest[..unit] {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60_000,
+            "h" => 3_600_000,
+            "d" => 86_400_000,
+            _ => return Err(error()),
+        };
+        // The constructors and the addition panic on overflow.
+        let millis = number.checked_mul(unit_millis).ok_or_else(error)?;
+        duration = duration
+            .checked_add(&Duration::milliseconds(millis))
+            .ok_or_else(error)?;
+        rest = &rest[unit..];
+    }
+    Ok(duration)
+}
+
+/// Parses the time of day like `07:30` or `07:30:15`.
+pub fn parse_time(value: &str) -> StdResult<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| format!("invalid time: `{}`", value))
+}
+
+/// Standard five-field cron expression: minute, hour, day of month, month and day of week.
+///
+/// Each field is either `*` or a comma-separated list of values and ranges optionally followed by a step,
+/// for example: `*/15`, `1-5` or `0,30`. Sunday is either `0` or `7`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CronExpression {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+
+    /// If both the day of month and day of week are restricted, either of them should match.
+    is_day_restricted: bool,
+    is_weekday_restricted: bool,
+}
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> StdResult<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields in the cron expression: `{}`", expression));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        weekdays[0] |= weekdays[7];
+        weekdays.truncate(7);
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            is_day_restricted: fields[2] != "*",
+            is_weekday_restricted: fields[4] != "*",
+        })
+    }
+
+    /// Returns the first matching minute after the timestamp.
+    pub fn next_after(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = timezone::localize(after)
+            .naive_local()
+            .with_second(0)?
+            .with_nanosecond(0)?
+            + Duration::minutes(1);
+        let mut date = start.date();
+        // Some expressions, like `0 0 31 2 *`, never match.
+        for _ in 0..(366 * 8) {
+            if self.matches_date(date) {
+                for hour in (0..24).filter(|hour| self.hours[*hour as usize]) {
+                    for minute in (0..60).filter(|minute| self.minutes[*minute as usize]) {
+                        let datetime = date.and_hms(hour, minute, 0);
+                        if datetime >= start {
+                            if let Some(timestamp) = timezone::from_local(&datetime) {
+                                return Some(timestamp);
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.succ();
+        }
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+        let day = self.days[date.day() as usize];
+        let weekday = self.weekdays[date.weekday().num_days_from_sunday() as usize];
+        if self.is_day_restricted && self.is_weekday_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+/// Parses the field into the flags indexed by the value.
+fn parse_field(field: &str, min: u32, max: u32) -> StdResult<Vec<bool>, String> {
+    let error = || format!("invalid cron field: `{}`", field);
+    let mut flags = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(index) => (&part[..index], part[index + 1..].parse::<u32>().map_err(|_| error())?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(index) = range.find('-') {
+            let start = range[..index].parse().map_err(|_| error())?;
+            (start, range[index + 1..].parse().map_err(|_| error())?)
+        } else {
+            let start = range.parse().map_err(|_| error())?;
+            (start, if step == 1 { start } else { max })
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(error());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            flags[value as usize] = true;
+        }
+    }
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_ok() -> Result {
+        assert_eq!(parse_duration("5m")?, Duration::minutes(5));
+        assert_eq!(parse_duration("1h30m")?, Duration::minutes(90));
+        assert_eq!(parse_duration("250ms")?, Duration::milliseconds(250));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_duration_err() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("9223372036854775807d").is_err());
+        assert!(parse_duration("9223372036854775807ms1ms").is_err());
+    }
+
+    #[test]
+    fn at_ok() -> Result {
+        let schedule = Schedule::At(parse_time("07:30")?);
+        let after = Local.ymd(2020, 12, 14).and_hms(8, 0, 0);
+        assert_eq!(
+            schedule.next_after(&after),
+            Some(Local.ymd(2020, 12, 15).and_hms(7, 30, 0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cron_every_two_hours_ok() -> Result {
+        let expression = CronExpression::parse("0 */2 * * *")?;
+        let after = Local.ymd(2020, 12, 14).and_hms(9, 15, 0);
+        assert_eq!(
+            expression.next_after(&after),
+            Some(Local.ymd(2020, 12, 14).and_hms(10, 0, 0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cron_weekdays_ok() -> Result {
+        // Saturday.
+        let after = Local.ymd(2020, 12, 19).and_hms(12, 0, 0);
+        let expression = CronExpression::parse("30 7 * * 1-5")?;
+        assert_eq!(
+            expression.next_after(&after),
+            Some(Local.ymd(2020, 12, 21).and_hms(7, 30, 0))
+        );
+        let expression = CronExpression::parse("0 9 * * 7")?;
+        assert_eq!(
+            expression.next_after(&after),
+            Some(Local.ymd(2020, 12, 20).and_hms(9, 0, 0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cron_never_matches_none() -> Result {
+        let expression = CronExpression::parse("0 0 31 2 *")?;
+        assert_eq!(expression.next_after(&Local::now()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn cron_err() {
+        assert!(CronExpression::parse("* * * *").is_err());
+        assert!(CronExpression::parse("60 * * * *").is_err());
+        assert!(CronExpression::parse("*/0 * * * *").is_err());
+    }
+}
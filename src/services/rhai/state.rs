@@ -0,0 +1,127 @@
+//! Persistent script state for Rhai.
+
+use rhai::{Dynamic, Engine, RegisterResultFn};
+
+use crate::prelude::*;
+use crate::services::rhai::json::{from_json, to_json};
+use crate::services::rhai::FnResult;
+
+/// Key-value storage which survives restarts, exposed to the scripts as `state`.
+///
+/// The values are stored as JSON in the `user_data` table, the keys are prefixed with the service ID.
+#[derive(Clone)]
+pub struct State {
+    service_id: String,
+    db: Connection,
+}
+
+impl State {
+    pub fn new(service_id: String, db: Connection) -> Self {
+        Self { service_id, db }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}::state::{}", self.service_id, key)
+    }
+}
+
+pub fn register_functions(engine: &mut Engine) {
+    engine.register_result_fn("get", get);
+    engine.register_result_fn("set", set);
+    engine.register_result_fn("set", set_with_ttl);
+    engine.register_result_fn("remove", remove);
+}
+
+/// Returns the stored value, or `()` if it's missing or expired.
+fn get(this: &mut State, key: &str) -> FnResult {
+    let value = task::block_on(this.db.get_user_data::<String>(&this.key(key))).map_err(|error| error.to_string())?;
+    match value {
+        Some(value) => Ok(from_json(
+            serde_json::from_str(&value).map_err(|error| error.to_string())?,
+        )),
+        None => Ok(().into()),
+    }
+}
+
+/// Stores the value forever.
+fn set(this: &mut State, key: &str, value: Dynamic) -> FnResult {
+    store(this, key, &value, None)
+}
+
+/// Stores the value for the number of seconds.
+fn set_with_ttl(this: &mut State, key: &str, value: Dynamic, ttl: i64) -> FnResult {
+    let expires_at = ttl
+        .checked_mul(1000)
+        .and_then(|millis| Local::now().checked_add_signed(chrono::Duration::milliseconds(millis)))
+        .ok_or_else(|| format!("the TTL is out of range: `{}`", ttl))?;
+    store(this, key, &value, Some(expires_at))
+}
+
+fn remove(this: &mut State, key: &str) -> FnResult {
+    task::block_on(this.db.delete_user_data(&this.key(key))).map_err(|error| error.to_string())?;
+    Ok(().into())
+}
+
+fn store(this: &State, key: &str, value: &Dynamic, expires_at: Option<DateTime<Local>>) -> FnResult {
+    let value = to_json(value)?.to_string();
+    task::block_on(this.db.set_user_data(&this.key(key), value, expires_at)).map_err(|error| error.to_string())?;
+    Ok(().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::Scope;
+
+    use super::*;
+
+    #[async_std::test]
+    async fn set_get_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let mut engine = Engine::new();
+        register_functions(&mut engine);
+
+        let mut scope = Scope::new();
+        scope.push_constant("state", State::new("rhai".into(), db.clone()));
+        engine.eval_with_scope::<()>(&mut scope, r#"state.set("last_run", #{day: 14, done: true})"#)?;
+        assert_eq!(
+            engine.eval_with_scope::<i64>(&mut scope, r#"state.get("last_run").day"#)?,
+            14
+        );
+        assert!(db.get_user_data::<String>("rhai::state::last_run").await?.is_some());
+
+        engine.eval_with_scope::<()>(&mut scope, r#"state.remove("last_run")"#)?;
+        assert!(engine
+            .eval_with_scope::<Dynamic>(&mut scope, r#"state.get("last_run")"#)?
+            .is::<()>());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn expired_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let mut engine = Engine::new();
+        register_functions(&mut engine);
+
+        let mut scope = Scope::new();
+        scope.push_constant("state", State::new("rhai".into(), db));
+        engine.eval_with_scope::<()>(&mut scope, r#"state.set("greeting", "hello", -1)"#)?;
+        assert!(engine
+            .eval_with_scope::<Dynamic>(&mut scope, r#"state.get("greeting")"#)?
+            .is::<()>());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn out_of_range_ttl_err() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let mut engine = Engine::new();
+        register_functions(&mut engine);
+
+        let mut scope = Scope::new();
+        scope.push_constant("state", State::new("rhai".into(), db));
+        assert!(engine
+            .eval_with_scope::<()>(&mut scope, r#"state.set("greeting", "hello", 9223372036854775807)"#)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+//! Timers, which call the script functions: `every`, `at`, `cron` and `after`.
+
+use std::sync::Mutex as StdMutex;
+
+use rhai::{Engine, FnPtr, RegisterFn, RegisterResultFn};
+
+use crate::prelude::*;
+use crate::services::rhai::schedule::{parse_duration, parse_time, CronExpression, Schedule};
+
+/// Registered timers shared between the script functions and the service loop.
+///
+/// The last runs of the recurring timers and the pending one-shot timers are stored in the `user_data` table,
+/// so that the missed runs are caught up after a restart.
+#[derive(Clone)]
+pub struct Timers {
+    service_id: String,
+    db: Connection,
+    inner: Arc<StdMutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    timers: Vec<Timer>,
+
+    /// The one-shot timers should be stored.
+    is_once_dirty: bool,
+}
+
+struct Timer {
+    /// Identifies the recurring timer in the database.
+    key: String,
+
+    schedule: Schedule,
+    callback: String,
+
+    /// `None` until the timer is initialised by the service loop.
+    next_run: Option<DateTime<Local>>,
+}
+
+impl Timers {
+    pub fn new(service_id: String, db: Connection) -> Self {
+        Self {
+            service_id,
+            db,
+            inner: Default::default(),
+        }
+    }
+
+    /// Adds the recurring timer, the `spec` and the callback identify the timer across restarts.
+    ///
+    /// Replaces the timer with the same key, so that re-registering it doesn't make it fire twice.
+    fn add(&self, spec: &str, schedule: Schedule, callback: FnPtr) {
+        let callback = callback.fn_name().to_string();
+        let timer = Timer {
+            key: format!("{}::timer::{}::{}", self.service_id, spec, callback),
+            schedule,
+            callback,
+            next_run: None,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        inner.timers.retain(|existing| existing.key != timer.key);
+        inner.timers.push(timer);
+    }
+
+    /// Adds the one-shot timer, replacing the pending one with the same callback.
+    fn add_once(&self, delay: chrono::Duration, callback: FnPtr) -> StdResult<(), String> {
+        let next_run = Local::now()
+            .checked_add_signed(delay)
+            .ok_or_else(|| format!("the delay is out of range: `{}`", delay))?;
+        let mut inner = self.inner.lock().unwrap();
+        inner.add_once(callback.fn_name().to_string(), next_run, true);
+        inner.is_once_dirty = true;
+        Ok(())
+    }
+
+    fn once_key(&self) -> String {
+        format!("{}::timers::once", self.service_id)
+    }
+
+    /// Restores the pending one-shot timers, should be called after the script is evaluated.
+    pub async fn restore(&self) -> Result {
+        if let Some(timers) = self.db.get_user_data::<Vec<(String, i64)>>(&self.once_key()).await? {
+            let mut inner = self.inner.lock().unwrap();
+            for (callback, next_run) in timers.into_iter() {
+                inner.add_once(callback, Local.timestamp_millis(next_run), false);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the callbacks to be called now and schedules their next runs.
+    pub async fn take_due(&self) -> Result<Vec<String>> {
+        let now = Local::now();
+
+        let new_keys: Vec<String> = {
+            let inner = self.inner.lock().unwrap();
+            let new_timers = inner.timers.iter().filter(|timer| timer.next_run.is_none());
+            new_timers.map(|timer| timer.key.clone()).collect()
+        };
+        for key in new_keys.into_iter() {
+            let last_run = self.db.get_user_data::<i64>(&key).await?;
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(timer) = inner.timers.iter_mut().find(|timer| timer.key == key) {
+                timer.next_run = match last_run {
+                    // Catch up the missed run, but only once.
+                    Some(last_run) => timer
+                        .schedule
+                        .next_after(&Local.timestamp_millis(last_run))
+                        .map(|next_run| next_run.min(now)),
+                    None => timer.schedule.next_after(&now),
+                };
+            }
+        }
+
+        let (callbacks, last_run_keys, once_timers) = {
+            let mut inner = self.inner.lock().unwrap();
+            let mut callbacks = Vec::new();
+            let mut last_run_keys = Vec::new();
+            let mut is_once_fired = false;
+            for timer in inner.timers.iter_mut() {
+                if timer.next_run.map_or(false, |next_run| next_run <= now) {
+                    callbacks.push(timer.callback.clone());
+                    timer.next_run = timer.schedule.next_after(&now);
+                    match timer.schedule {
+                        Schedule::Once => is_once_fired = true,
+                        _ => last_run_keys.push(timer.key.clone()),
+                    }
+                }
+            }
+            inner.timers.retain(|timer| timer.next_run.is_some());
+            let once_timers = if inner.is_once_dirty || is_once_fired {
+                inner.is_once_dirty = false;
+                Some(inner.once_timers())
+            } else {
+                None
+            };
+            (callbacks, last_run_keys, once_timers)
+        };
+
+        for key in last_run_keys.into_iter() {
+            self.db.set_user_data(&key, now.timestamp_millis(), None).await?;
+        }
+        if let Some(once_timers) = once_timers {
+            self.db.set_user_data(&self.once_key(), once_timers, None).await?;
+        }
+        Ok(callbacks)
+    }
+
+    /// Returns the earliest next run of the initialised timers.
+    pub fn next_run(&self) -> Option<DateTime<Local>> {
+        let inner = self.inner.lock().unwrap();
+        inner.timers.iter().filter_map(|timer| timer.next_run).min()
+    }
+}
+
+impl Inner {
+    fn add_once(&mut self, callback: String, next_run: DateTime<Local>, replace: bool) {
+        let existing = self
+            .timers
+            .iter()
+            .position(|timer| matches!(timer.schedule, Schedule::Once) && timer.callback == callback);
+        match existing {
+            Some(index) if replace => {
+                self.timers.remove(index);
+            }
+            Some(_) => return,
+            None => (),
+        }
+        self.timers.push(Timer {
+            key: String::new(),
+            schedule: Schedule::Once,
+            callback,
+            next_run: Some(next_run),
+        });
+    }
+
+    /// Returns the pending one-shot timers to be stored.
+    fn once_timers(&self) -> Vec<(String, i64)> {
+        self.timers
+            .iter()
+            .filter(|timer| matches!(timer.schedule, Schedule::Once))
+            .filter_map(|timer| Some((timer.callback.clone(), timer.next_run?.timestamp_millis())))
+            .collect()
+    }
+}
+
+pub fn register_functions(engine: &mut Engine, timers: &Timers) {
+    {
+        let timers = timers.clone();
+        engine.register_result_fn("every", move |interval: &str, callback: FnPtr| {
+            let duration = parse_duration(interval)?;
+            if duration <= chrono::Duration::zero() {
+                return Err(format!("the interval must be positive: `{}`", interval).into());
+            }
+            timers.add(&format!("every {}", interval), Schedule::Every(duration), callback);
+            Ok(().into())
+        });
+    }
+    {
+        let timers = timers.clone();
+        engine.register_result_fn("at", move |time: &str, callback: FnPtr| {
+            let schedule = Schedule::At(parse_time(time)?);
+            timers.add(&format!("at {}", time), schedule, callback);
+            Ok(().into())
+        });
+    }
+    {
+        let timers = timers.clone();
+        engine.register_result_fn("cron", move |expression: &str, callback: FnPtr| {
+            let schedule = Schedule::Cron(Box::new(CronExpression::parse(expression)?));
+            timers.add(&format!("cron {}", expression), schedule, callback);
+            Ok(().into())
+        });
+    }
+    {
+        let timers = timers.clone();
+        engine.register_result_fn("after", move |delay: &str, callback: FnPtr| {
+            timers.add_once(parse_duration(delay)?, callback)?;
+            Ok(().into())
+        });
+    }
+    {
+        let timers = timers.clone();
+        engine.register_result_fn("after", move |seconds: i64, callback: FnPtr| {
+            let millis = seconds
+                .checked_mul(1000)
+                .ok_or_else(|| format!("the delay is out of range: `{}`", seconds))?;
+            timers.add_once(chrono::Duration::milliseconds(millis), callback)?;
+            Ok(().into())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn new_timers(db: &Connection, script: &str) -> Result<Timers> {
+        let timers = Timers::new("rhai".into(), db.clone());
+        let mut engine = Engine::new();
+        register_functions(&mut engine, &timers);
+        engine.consume(script)?;
+        timers.restore().await?;
+        Ok(timers)
+    }
+
+    #[async_std::test]
+    async fn every_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let timers = new_timers(&db, r#"fn on_timer() {} every("5m", Fn("on_timer"));"#).await?;
+        assert!(timers.take_due().await?.is_empty());
+        let next_run = timers.next_run().ok_or_else(|| anyhow!("the timer is not scheduled"))?;
+        assert!(next_run > Local::now() + chrono::Duration::minutes(4));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn missed_run_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let last_run = Local::now() - chrono::Duration::hours(1);
+        db.set_user_data("rhai::timer::every 5m::on_timer", last_run.timestamp_millis(), None)
+            .await?;
+
+        let timers = new_timers(&db, r#"fn on_timer() {} every("5m", Fn("on_timer"));"#).await?;
+        assert_eq!(timers.take_due().await?, vec!["on_timer".to_string()]);
+        assert!(timers.take_due().await?.is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn after_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let timers = new_timers(
+            &db,
+            r#"fn on_timer() {} after(0, Fn("on_timer")); after("1h", Fn("on_timer"));"#,
+        )
+        .await?;
+        assert!(timers.take_due().await?.is_empty(), "the timer should be rescheduled");
+        assert_eq!(
+            db.get_user_data::<Vec<(String, i64)>>("rhai::timers::once")
+                .await?
+                .map(|timers| timers.len()),
+            Some(1)
+        );
+
+        let timers = new_timers(&db, "fn on_timer() {}").await?;
+        assert!(timers.next_run().is_some(), "the timer should be restored");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn add_replaces_same_key_ok() -> Result {
+        let db = Connection::open(":memory:").await?;
+        let timers = new_timers(
+            &db,
+            r#"fn on_timer() {} every("5m", Fn("on_timer")); every("5m", Fn("on_timer"));"#,
+        )
+        .await?;
+        let last_run = Local::now() - chrono::Duration::hours(1);
+        db.set_user_data("rhai::timer::every 5m::on_timer", last_run.timestamp_millis(), None)
+            .await?;
+        assert_eq!(timers.take_due().await?, vec!["on_timer".to_string()]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn out_of_range_delay_err() -> Result {
+        let db = Connection::open(":memory:").await?;
+        assert!(
+            new_timers(&db, r#"fn on_timer() {} after(9223372036854775807, Fn("on_timer"));"#)
+                .await
+                .is_err()
+        );
+        assert!(
+            new_timers(&db, r#"fn on_timer() {} after("106751991167d", Fn("on_timer"));"#)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn invalid_schedule_err() -> Result {
+        let db = Connection::open(":memory:").await?;
+        assert!(new_timers(&db, r#"fn on_timer() {} cron("* * *", Fn("on_timer"));"#)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+//! Web Push methods for Rhai.
+
+use rhai::{Engine, RegisterFn};
+
+use crate::prelude::*;
+use crate::services::web_push::Notifier;
+
+pub fn register_functions(engine: &mut Engine) {
+    engine.register_fn("send", send);
+}
+
+/// Sends the notification to all the subscribers of the service.
+fn send(this: &mut Notifier, title: String, body: String) {
+    let this = this.clone();
+    task::spawn(async move {
+        let _ = this
+            .send(&title, &body)
+            .await
+            .log(|| "failed to send the push notification");
+    });
+}
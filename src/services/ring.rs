@@ -18,7 +18,7 @@ pub struct Ring {
 #[derive(Deserialize, Debug, Clone, Serialize)]
 struct Secrets {
     /// Initial `refresh_token` used to get an active access token for the first time.
-    initial_refresh_token: String,
+    initial_refresh_token: Secret,
 }
 
 const fn default_interval_millis() -> u64 {
@@ -197,7 +197,7 @@ impl Ring {
                 let refresh_token = db
                     .get_user_data::<String>(&refresh_token_key)
                     .await?
-                    .unwrap_or_else(|| self.secrets.initial_refresh_token.clone());
+                    .unwrap_or_else(|| self.secrets.initial_refresh_token.to_string());
                 let response = CLIENT
                     .post("https://oauth.ring.com/oauth/token")
                     .body(
@@ -19,10 +19,10 @@ pub struct Solar {
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Secrets {
     /// Latitude in [WGS84](https://en.wikipedia.org/wiki/World_Geodetic_System) system, ranging from `-90.0` to `90.0`.
-    latitude: f64,
+    latitude: Secret<f64>,
 
     /// Longitude in [WGS84](https://en.wikipedia.org/wiki/World_Geodetic_System) system, ranging from `-180.0` to `180.0`
-    longitude: f64,
+    longitude: Secret<f64>,
 }
 
 /// Defaults to one minute.
@@ -49,7 +49,7 @@ impl Solar {
 
     async fn loop_(&self, service_id: &str, tx: &mut Sender) -> Result {
         let now = Utc::now();
-        match calc_sunrise_and_set(now, self.secrets.latitude, self.secrets.longitude)? {
+        match calc_sunrise_and_set(now, *self.secrets.latitude, *self.secrets.longitude)? {
             SunriseAndSet::Daylight(sunrise, sunset) => {
                 if now < sunrise {
                     Message::new(format!("{}::before::sunrise", service_id))
@@ -28,8 +28,8 @@ pub struct Tado {
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Secrets {
-    email: String,
-    password: String,
+    email: Secret,
+    password: Secret,
 }
 
 /// Creates an empty token by default.
@@ -184,8 +184,8 @@ impl Tado {
                     ("client_secret", CLIENT_SECRET),
                     ("grant_type", "password"),
                     ("scope", SCOPE),
-                    ("username", &self.secrets.email),
-                    ("password", &self.secrets.password),
+                    ("username", self.secrets.email.as_str()),
+                    ("password", self.secrets.password.as_str()),
                 ],
             )?)
             .recv_json::<Token>()
@@ -17,7 +17,7 @@ pub struct Telegram {
 /// Secrets section.
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Secrets {
-    pub token: String,
+    pub token: Secret,
 }
 
 impl Telegram {
@@ -116,7 +116,8 @@ impl Telegram {
         CLIENT
             .post(format!(
                 "https://api.telegram.org/bot{}/{}",
-                self.secrets.token, method_name,
+                self.secrets.token.as_str(),
+                method_name,
             ))
             .body(body)
             .recv_json::<TelegramResponse<R>>()
@@ -0,0 +1,213 @@
+//! [Web Push](https://developer.mozilla.org/en-US/docs/Web/API/Push_API) notifications
+//! to the browsers subscribed via the web interface.
+
+use regex::Regex;
+use rocket::http::uri::Uri;
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushMessage, WebPushMessageBuilder, URL_SAFE_NO_PAD,
+};
+
+use crate::core::db::push_subscription::PushSubscription;
+use crate::prelude::*;
+use crate::services::prelude::*;
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct WebPush {
+    /// Contact URI of the application server, for example: `mailto:admin@example.com`.
+    /// Push services use it to reach out in case of problems.
+    pub subject: String,
+
+    /// VAPID public key: uncompressed P-256 point, URL-safe base64-encoded.
+    pub public_key: String,
+
+    /// Notify about the logged readings of the sensors which IDs match any of the patterns.
+    #[serde(with = "serde_regex", default)]
+    pub sensor_patterns: Vec<Regex>,
+
+    /// How long a push service should keep an undelivered notification, in seconds.
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+
+    pub secrets: Secrets,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct Secrets {
+    /// VAPID private key: raw P-256 scalar, URL-safe base64-encoded.
+    pub private_key: Secret,
+}
+
+/// Notification payload, which is displayed by the service worker.
+#[derive(Serialize)]
+struct Notification<'a> {
+    title: &'a str,
+    body: &'a str,
+
+    /// Page to open on click, relative to the web interface root.
+    url: Option<String>,
+}
+
+fn default_ttl() -> u32 {
+    86400
+}
+
+/// Sends the notifications to the subscribers of the service.
+#[derive(Clone)]
+pub struct Notifier {
+    service_id: String,
+    service: WebPush,
+    db: Connection,
+}
+
+impl WebPush {
+    pub fn spawn(self, service_id: String, bus: &mut Bus, db: &Connection) -> Result {
+        let mut rx = bus.add_rx();
+        let notifier = self.clone().into_notifier(service_id.clone(), db.clone());
+
+        task::spawn(async move {
+            while let Some(message) = rx.next().await {
+                if message.type_ != MessageType::ReadLogged || !self.is_notified(&message.sensor.id) {
+                    continue;
+                }
+                if let Value::Blob(_) = message.reading.value {
+                    debug!("[{}] `{}` is binary, skipped.", service_id, message.sensor.id);
+                    continue;
+                }
+                let title = message
+                    .sensor
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| message.sensor.id.clone());
+                let body = format!("{} – {}", plain_text(&message.reading.value), message.sensor.location);
+                let url = format!("sensors/{}", Uri::percent_encode(&message.sensor.id));
+                let _ = notifier
+                    .send_with_url(&title, &body, Some(url))
+                    .await
+                    .log(|| format!("[{}] failed to notify about `{}`", service_id, message.sensor.id));
+            }
+            unreachable!();
+        });
+
+        Ok(())
+    }
+
+    pub fn into_notifier(self, service_id: String, db: Connection) -> Notifier {
+        Notifier {
+            service_id,
+            service: self,
+            db,
+        }
+    }
+
+    fn is_notified(&self, sensor_id: &str) -> bool {
+        self.sensor_patterns.iter().any(|pattern| pattern.is_match(sensor_id))
+    }
+
+    /// Builds the encrypted VAPID-signed notification.
+    fn build_message(&self, subscription: &PushSubscription, payload: &[u8]) -> Result<WebPushMessage> {
+        let subscription_info = SubscriptionInfo::new(
+            &subscription.endpoint,
+            &subscription.keys.p256dh,
+            &subscription.keys.auth,
+        );
+        let mut signature_builder =
+            VapidSignatureBuilder::from_base64(self.secrets.private_key.as_str(), URL_SAFE_NO_PAD, &subscription_info)?;
+        signature_builder.add_claim("sub", self.subject.as_str());
+
+        let mut builder = WebPushMessageBuilder::new(&subscription_info)?;
+        builder.set_ttl(self.ttl);
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+        builder.set_vapid_signature(signature_builder.build()?);
+        Ok(builder.build()?)
+    }
+}
+
+impl Notifier {
+    pub async fn send(&self, title: &str, body: &str) -> Result {
+        self.send_with_url(title, body, None).await
+    }
+
+    /// Sends the notification to all the subscribers.
+    /// The subscriptions, which are gone, are deleted.
+    pub async fn send_with_url(&self, title: &str, body: &str, url: Option<String>) -> Result {
+        let payload = serde_json::to_vec(&Notification { title, body, url })?;
+        for subscription in self.db.select_push_subscriptions(&self.service_id).await? {
+            let message = self.service.build_message(&subscription, &payload)?;
+            match post(message).await {
+                Ok(_) => debug!("[{}] Sent to {}.", self.service_id, subscription.endpoint),
+                Err(error) if is_gone(&error) => {
+                    info!("[{}] Unsubscribed: {}.", self.service_id, subscription.endpoint);
+                    self.db.delete_push_subscription(&subscription.endpoint).await?;
+                }
+                Err(error) => error!(
+                    "[{}] Failed to send to {}: {}",
+                    self.service_id, subscription.endpoint, error,
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Delivers the message to the push service.
+async fn post(message: WebPushMessage) -> surf::Result<surf::Response> {
+    let mut request = CLIENT
+        .post(message.endpoint.to_string())
+        .header("TTL", message.ttl.to_string());
+    if let Some(payload) = message.payload {
+        request = request.header("Content-Encoding", payload.content_encoding.to_str());
+        for (name, value) in payload.crypto_headers.into_iter() {
+            request = request.header(name, value);
+        }
+        request = request.body(payload.content);
+    }
+    request.await
+}
+
+/// Tells whether the subscription has expired or the user has revoked the permission.
+fn is_gone(error: &surf::Error) -> bool {
+    error.status() == 404 || error.status() == 410
+}
+
+/// Formats the value with its units as plain text, since the notifications don't render HTML.
+fn plain_text(value: &Value) -> String {
+    match value {
+        Value::Text(text) | Value::StringEnum(text) | Value::ImageUrl(text) => text.clone(),
+        Value::Boolean(value) => value.to_string(),
+        value => match value.to_f64() {
+            Some(number) => format!("{} {}", number * value.chart_multiplier(), value.chart_unit())
+                .trim_end()
+                .to_string(),
+            None => value.type_name().to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_notified_ok() -> Result {
+        let service: WebPush = toml::from_str(
+            r#"
+                subject = "mailto:admin@example.com"
+                public_key = "key"
+                sensor_patterns = ["^ring::.*::ding$"]
+
+                [secrets]
+                private_key = "secret"
+            "#,
+        )?;
+        assert!(service.is_notified("ring::doorbell::ding"));
+        assert!(!service.is_notified("ring::doorbell::motion"));
+        Ok(())
+    }
+
+    #[test]
+    fn plain_text_ok() {
+        assert_eq!(plain_text(&Value::Temperature(21.5)), "21.5 ℃");
+        assert_eq!(plain_text(&Value::Counter(3)), "3");
+        assert_eq!(plain_text(&Value::StringEnum("heating".into())), "heating");
+    }
+}
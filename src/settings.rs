@@ -2,11 +2,16 @@
 
 use crate::prelude::*;
 use crate::services;
+use regex::Regex;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+pub use self::secret::Secret;
+
+pub mod secret;
+
 /// Read the settings file.
 pub fn read<P: AsRef<Path> + std::fmt::Debug>(paths: Vec<P>) -> Result<Settings> {
     Ok(toml::from_str(
@@ -37,16 +42,28 @@ pub struct Settings {
     #[serde(default = "HashMap::new")]
     pub services: HashMap<String, Service>,
 
+    /// User-defined dashboards.
+    ///
+    /// Each entry is a pair of dashboard ID and the dashboard layout.
+    /// The dashboard is available at `/dashboards/<dashboard_id>`.
+    #[serde(default = "HashMap::new")]
+    pub dashboards: HashMap<String, Dashboard>,
+
     /// Separate section for sensitive settings.
     #[serde(default)]
     pub secrets: SecretSettings,
+
+    /// [Time zone](https://en.wikipedia.org/wiki/List_of_tz_database_time_zones) to display the timestamps
+    /// and to resolve the calendar dates and times, for example: `Europe/Amsterdam`. Defaults to the host one.
+    #[serde(default)]
+    pub timezone: Option<chrono_tz::Tz>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct SecretSettings {
     /// Optional Sentry DSN for monitoring.
     #[serde(default)]
-    pub sentry_dsn: Option<String>,
+    pub sentry_dsn: Option<Secret>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -55,9 +72,193 @@ pub struct HttpSettings {
     #[serde(default = "default_http_port")]
     pub port: u16,
 
+    /// Address to bind the web server to.
+    #[serde(default = "default_http_address")]
+    pub address: String,
+
+    /// Serve HTTPS instead of the plain HTTP.
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+
+    /// Path prefix, if the web interface is published under a reverse proxy sub-path, for example: `/iot`.
+    #[serde(default)]
+    pub base_path: String,
+
+    /// Trust `X-Forwarded-Prefix`, `X-Forwarded-Proto` and `X-Forwarded-For` headers set by a reverse proxy.
+    ///
+    /// Enable it only if the web server isn't reachable other than via the reverse proxy.
+    #[serde(default)]
+    pub trust_forwarded_headers: bool,
+
     /// Disable the web server.
     #[serde(default)]
     pub disabled: bool,
+
+    /// Number of the web server worker threads.
+    ///
+    /// Each browser tab with live updates occupies a worker, so it should be big enough.
+    #[serde(default = "default_http_workers")]
+    pub workers: u16,
+
+    /// Web interface users. Each entry is a pair of user name and user settings.
+    ///
+    /// If no users are defined, the authentication is disabled.
+    #[serde(default = "HashMap::new")]
+    pub users: HashMap<String, UserSettings>,
+
+    /// Sensor IDs to offer as shortcuts of the installed web app.
+    #[serde(default)]
+    pub favourites: Vec<String>,
+
+    /// Prometheus `/metrics` endpoint settings.
+    #[serde(default)]
+    pub metrics: MetricsSettings,
+
+    /// Web interface language. If not set, it's negotiated with the browser via `Accept-Language`.
+    #[serde(default)]
+    pub locale: Option<Locale>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, Default)]
+pub struct MetricsSettings {
+    /// Export only the sensors whose IDs match any of the patterns. All the sensors are exported if empty.
+    #[serde(with = "serde_regex", default)]
+    pub include: Vec<Regex>,
+
+    /// Don't export the sensors whose IDs match any of the patterns.
+    #[serde(with = "serde_regex", default)]
+    pub exclude: Vec<Regex>,
+}
+
+impl MetricsSettings {
+    /// Tells whether the sensor should be exported.
+    pub fn is_exported(&self, sensor_id: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| pattern.is_match(sensor_id)))
+            && !self.exclude.iter().any(|pattern| pattern.is_match(sensor_id))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct TlsSettings {
+    /// Path to the PEM certificate chain.
+    pub certificate_path: String,
+
+    /// Path to the PEM private key.
+    pub private_key_path: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct UserSettings {
+    #[serde(default)]
+    pub role: Role,
+
+    pub secrets: UserSecrets,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct UserSecrets {
+    /// [Argon2](https://en.wikipedia.org/wiki/Argon2) password hash, run `my-iot --hash-password` to generate one.
+    pub password_hash: Secret,
+
+    /// Tokens to access the JSON API with the `Authorization: Bearer <token>` header.
+    #[serde(default)]
+    pub api_tokens: Vec<Secret>,
+}
+
+/// Web interface user role.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Role {
+    /// Allowed to view the sensors.
+    ReadOnly,
+
+    /// Allowed to view the settings, to control and to manage the sensors.
+    Admin,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct Dashboard {
+    /// Dashboard title, defaults to the dashboard ID.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub sections: Vec<DashboardSection>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct DashboardSection {
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Tiles in the display order.
+    #[serde(default)]
+    pub tiles: Vec<Tile>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct Tile {
+    pub sensor_id: String,
+
+    /// Tile title, defaults to the sensor title.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub widget: Widget,
+
+    #[serde(default)]
+    pub size: TileSize,
+
+    /// `Gauge` lower bound.
+    #[serde(default)]
+    pub min: f64,
+
+    /// `Gauge` upper bound.
+    #[serde(default = "default_tile_max")]
+    pub max: f64,
+
+    /// `Sparkline` period.
+    #[serde(default = "default_tile_minutes")]
+    pub minutes: i64,
+}
+
+/// The way the sensor reading is displayed on a dashboard tile.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Widget {
+    /// The latest value, like on the home page.
+    Value,
+
+    /// The latest value with a small chart of the recent readings.
+    Sparkline,
+
+    /// The latest value within the `min` and `max` bounds.
+    Gauge,
+
+    /// Full-size image for `ImageUrl` sensors.
+    Image,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TileSize {
+    Small,
+    Medium,
+    Large,
+    Wide,
+}
+
+/// Web interface language.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Nl,
+    Ru,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::En
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -101,6 +302,9 @@ pub enum Service {
 
     Threshold(Box<services::threshold::Threshold>),
 
+    /// [Web Push](https://developer.mozilla.org/en-US/docs/Web/API/Push_API) notifications.
+    WebPush(Box<services::web_push::WebPush>),
+
     /// [YouLess](https://www.youless.nl/home.html) kWh meter to ethernet bridge.
     YouLess(Box<services::youless::YouLess>),
 }
@@ -111,11 +315,38 @@ impl Default for SecretSettings {
     }
 }
 
+impl Default for Widget {
+    fn default() -> Self {
+        Widget::Value
+    }
+}
+
+impl Default for TileSize {
+    fn default() -> Self {
+        TileSize::Medium
+    }
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        Role::ReadOnly
+    }
+}
+
 impl Default for HttpSettings {
     fn default() -> Self {
         Self {
             port: default_http_port(),
+            address: default_http_address(),
+            tls: None,
+            base_path: String::new(),
+            trust_forwarded_headers: false,
             disabled: false,
+            workers: default_http_workers(),
+            users: HashMap::new(),
+            favourites: Vec::new(),
+            metrics: MetricsSettings::default(),
+            locale: None,
         }
     }
 }
@@ -132,6 +363,22 @@ pub fn default_http_port() -> u16 {
     8081
 }
 
+fn default_http_address() -> String {
+    "0.0.0.0".into()
+}
+
+fn default_http_workers() -> u16 {
+    32
+}
+
+fn default_tile_max() -> f64 {
+    100.0
+}
+
+fn default_tile_minutes() -> i64 {
+    60
+}
+
 fn default_database_path() -> String {
     "my-iot.sqlite3".into()
 }
@@ -0,0 +1,109 @@
+//! Secret settings values.
+
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::Serializer;
+
+use crate::prelude::*;
+
+/// Replaces the secret value on the settings page and in the logs.
+const MASK: &str = "********";
+
+/// Sensitive settings value which is masked when displayed.
+///
+/// Besides a plain value, it may be read from an environment variable, `{ env = "VARIABLE" }`,
+/// or from a file, `{ file = "/path/to/secret" }`. The file contents are trimmed.
+#[derive(Clone, PartialEq)]
+pub struct Secret<T = String>(T);
+
+/// Where the secret value comes from.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Source<T> {
+    Env { env: String },
+    File { file: String },
+    Value(T),
+}
+
+impl<T> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+        serializer.serialize_str(MASK)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Secret<T>
+where
+    T: Deserialize<'de> + FromStr,
+    T::Err: std::fmt::Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+        let value = match Source::<T>::deserialize(deserializer)? {
+            Source::Value(value) => value,
+            Source::Env { env } => std::env::var(&env)
+                .map_err(|error| D::Error::custom(format!("failed to read `{}`: {}", env, error)))?
+                .parse()
+                .map_err(|error| D::Error::custom(format!("failed to parse `{}`: {}", env, error)))?,
+            Source::File { file } => std::fs::read_to_string(&file)
+                .map_err(|error| D::Error::custom(format!("failed to read `{}`: {}", file, error)))?
+                .trim()
+                .parse()
+                .map_err(|error| D::Error::custom(format!("failed to parse `{}`: {}", file, error)))?,
+        };
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Secrets {
+        token: Secret,
+    }
+
+    #[test]
+    fn plain_value_ok() -> Result {
+        let secrets: Secrets = toml::from_str(r#"token = "foo""#)?;
+        assert_eq!(secrets.token.as_str(), "foo");
+        Ok(())
+    }
+
+    #[test]
+    fn env_ok() -> Result {
+        std::env::set_var("MY_IOT_TEST_SECRET", "bar");
+        let secrets: Secrets = toml::from_str(r#"token = { env = "MY_IOT_TEST_SECRET" }"#)?;
+        assert_eq!(secrets.token.as_str(), "bar");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_env_err() {
+        assert!(toml::from_str::<Secrets>(r#"token = { env = "MY_IOT_TEST_MISSING_SECRET" }"#).is_err());
+    }
+
+    #[test]
+    fn masked_ok() -> Result {
+        let secrets: Secrets = toml::from_str(r#"token = "foo""#)?;
+        assert!(!format!("{:?}", secrets).contains("foo"));
+        assert!(!toml::to_string(&secrets)?.contains("foo"));
+        Ok(())
+    }
+}
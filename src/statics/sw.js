@@ -1 +1,123 @@
 'use strict';
+
+// Bump the version to drop the outdated caches.
+const STATIC_CACHE = 'my-iot-static-v1';
+const STATE_CACHE = 'my-iot-state-v1';
+
+// Paths are relative to the service worker, so that a reverse proxy sub-path is respected.
+const STATIC_ASSETS = [
+    'static/bulma.min.css',
+    'static/bulma-prefers-dark.css',
+    'static/fontawesome.css',
+    'static/Chart.bundle.min.js',
+    'static/favicon-16x16.png',
+    'static/favicon-32x32.png',
+    'static/apple-touch-icon.png',
+    'static/android-chrome-192x192.png',
+    'static/android-chrome-512x512.png',
+    'webfonts/fa-solid-900.woff2',
+    'webfonts/fa-regular-400.woff2',
+    'webfonts/fa-brands-400.woff2',
+    'favicon.ico',
+];
+
+// Only the latest versions of these pages are kept for offline use, without the custom ranges.
+// The downloads, like the blobs and the CSV history, are never cached.
+const CACHED_PAGES = [/^$/, /^dashboards\/[^/]+$/, /^sensors\/[^/]+$/];
+
+// The state cache is trimmed to the most recently fetched pages.
+const MAX_CACHED_PAGES = 50;
+
+self.addEventListener('install', event => {
+    event.waitUntil(caches.open(STATIC_CACHE).then(cache => cache.addAll(STATIC_ASSETS)).then(() => self.skipWaiting()));
+});
+
+self.addEventListener('activate', event => {
+    event.waitUntil(
+        caches.keys()
+            .then(keys => Promise.all(keys
+                .filter(key => key !== STATIC_CACHE && key !== STATE_CACHE)
+                .map(key => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+});
+
+self.addEventListener('fetch', event => {
+    const request = event.request;
+    if (request.method !== 'GET') {
+        return;
+    }
+    const url = new URL(request.url);
+    if (url.origin !== self.location.origin || !url.href.startsWith(self.registration.scope)) {
+        return;
+    }
+    const path = url.href.substring(self.registration.scope.length).split('?')[0];
+    if (path.startsWith('static/') || path.startsWith('webfonts/') || path === 'favicon.ico') {
+        event.respondWith(cacheFirst(request));
+    } else if (request.mode === 'navigate' && url.search === '' && CACHED_PAGES.some(page => page.test(path))) {
+        event.respondWith(networkFirst(request));
+    }
+});
+
+// The page asks to drop the sensor state, for instance, on signing out.
+self.addEventListener('message', event => {
+    if (event.data === 'clear-state') {
+        event.waitUntil(caches.delete(STATE_CACHE));
+    }
+});
+
+/** Serves the static assets from the cache, they're versioned by the server release. */
+function cacheFirst(request) {
+    return caches.match(request).then(cached => cached || fetch(request).then(response => {
+        if (response.ok) {
+            const copy = response.clone();
+            caches.open(STATIC_CACHE).then(cache => cache.put(request, copy));
+        }
+        return response;
+    }));
+}
+
+/** Fetches the latest page, and falls back to the last fetched one when the server is unreachable. */
+function networkFirst(request) {
+    return fetch(request).then(response => {
+        if (response.ok && !response.redirected) {
+            const copy = response.clone();
+            caches.open(STATE_CACHE).then(cache => cache.put(request, copy).then(() => trim(cache)));
+        }
+        return response;
+    }).catch(error => caches.open(STATE_CACHE).then(cache => cache.match(request)).then(cached => {
+        if (!cached) {
+            throw error;
+        }
+        return markOffline(cached);
+    }));
+}
+
+/** Drops the least recently fetched pages, the cache keys are listed in the order of insertion. */
+function trim(cache) {
+    return cache.keys().then(keys => Promise.all(keys
+        .slice(0, Math.max(0, keys.length - MAX_CACHED_PAGES))
+        .map(key => cache.delete(key))));
+}
+
+/** Marks the cached page, so that it shows the offline banner. */
+function markOffline(response) {
+    return response.text().then(html => new Response(
+        html.replace('<body', '<body data-offline="true"'),
+        {status: response.status, statusText: response.statusText, headers: response.headers},
+    ));
+}
+
+self.addEventListener('push', event => {
+    const notification = event.data ? event.data.json() : {title: 'My IoT'};
+    event.waitUntil(self.registration.showNotification(notification.title, {
+        body: notification.body,
+        icon: new URL('static/android-chrome-192x192.png', self.registration.scope).href,
+        data: {url: new URL(notification.url || './', self.registration.scope).href},
+    }));
+});
+
+self.addEventListener('notificationclick', event => {
+    event.notification.close();
+    event.waitUntil(self.clients.openWindow(event.notification.data.url));
+});
@@ -4,98 +4,372 @@ use std::io::Cursor;
 
 use chrono::Duration;
 use itertools::Itertools;
+use regex::Regex;
 use rocket::config::Environment;
+use rocket::fairing::AdHoc;
 use rocket::http::hyper::header::ETag;
+use rocket::http::uri::Uri;
 use rocket::http::ContentType;
+use rocket::http::Cookies;
 use rocket::http::Status;
+use rocket::request::Form;
 use rocket::response::content::Content;
 use rocket::response::Redirect;
-use rocket::{delete, get, routes, uri, Config, Response, Rocket, State};
+use rocket::{
+    catch, catchers, delete, get, post, routes, uri, Config, FromForm, Request, Responder, Response, Rocket, State,
+};
 use rocket_contrib::json::Json;
 
+use crate::core::metrics as process_metrics;
 use crate::prelude::*;
-use crate::settings::Settings;
+use crate::settings::{Service, Settings};
+use crate::web::annotation_form::AnnotationForm;
+use crate::web::auth::{Admin, User};
+use crate::web::bulk_action::{BulkAction, BulkActionForm};
 use crate::web::cached_content::Cached;
+use crate::web::comparison::ComparisonForm;
+use crate::web::context::Context;
+use crate::web::history::HistoryQuery;
 use crate::web::if_none_match::IfNoneMatch;
+use crate::web::live::{Broadcaster, EventStream};
+use crate::web::media::sniff_mime_type;
+use crate::web::range::Range;
+use crate::web::search::{Facets, SearchQuery};
 use crate::web::to_html_string::ToHtmlString;
 use std::convert::TryInto;
 
+mod annotation_form;
+mod api;
+mod auth;
+mod bulk_action;
 mod cached_content;
+mod comparison;
+mod context;
+mod dashboard;
 mod entity_tag;
+mod history;
+mod i18n;
 mod if_none_match;
+mod live;
+mod media;
+mod metrics;
+mod range;
+mod search;
 mod templates;
+mod timeline;
 mod to_html_string;
 
 const STATIC_MAX_AGE_SECS: u32 = 3600;
 
+pub use auth::hash_password;
+
 /// Start the web application.
-pub fn start_server(settings: &Settings, db: Connection) -> Result {
-    info!("Starting web server on port {}…", settings.http.port);
-    Err(make_rocket(settings, db)?.launch().into())
+///
+/// `tx` is used to send messages to the bus, and `rx` is used to stream live updates to browsers.
+pub fn start_server(settings: &Settings, db: Connection, tx: Sender, rx: Receiver) -> Result {
+    info!(
+        "Starting web server on {}:{}{}…",
+        settings.http.address,
+        settings.http.port,
+        if settings.http.tls.is_some() { " with TLS" } else { "" },
+    );
+    Err(make_rocket(settings, db, tx, rx)?.launch().into())
 }
 
 /// Builds the [Rocket](https://rocket.rs/) application.
-fn make_rocket(settings: &Settings, db: Connection) -> Result<Rocket> {
-    Ok(rocket::custom(
-        Config::build(Environment::Production)
-            .port(settings.http.port)
-            .keep_alive(600)
-            .finalize()?,
-    )
-    .manage(db)
-    .manage(settings.clone())
-    .mount(
-        "/",
-        routes![
-            get_index,
-            get_settings,
-            get_sensor,
-            delete_sensor,
-            get_sensor_json,
-            get_favicon,
-            get_favicon_16,
-            get_favicon_32,
-            get_apple_touch_icon,
-            get_android_chrome_192,
-            get_android_chrome_512,
-            get_bulma_css,
-            get_bulma_prefers_dark,
-            get_chart_js,
-            get_font_awesome,
-            get_webfonts_fa_solid_900,
-            get_webfonts_fa_regular_400,
-            get_webfonts_fa_brands_400,
-            get_sw_js,
-            get_webmanifest,
-        ],
-    ))
+fn make_rocket(settings: &Settings, db: Connection, tx: Sender, rx: Receiver) -> Result<Rocket> {
+    let config = Config::build(Environment::Production)
+        .address(&settings.http.address)
+        .port(settings.http.port)
+        .workers(settings.http.workers)
+        .keep_alive(600);
+    let config = match &settings.http.tls {
+        Some(tls) => config.tls(&tls.certificate_path, &tls.private_key_path),
+        None => config,
+    };
+    Ok(rocket::custom(config.finalize()?)
+        .manage(db)
+        .manage(tx)
+        .manage(Broadcaster::spawn(rx))
+        .manage(settings.clone())
+        .attach(AdHoc::on_request("Locale", |request, _| {
+            if let Some(settings) = request.guard::<State<Settings>>().succeeded() {
+                i18n::set_current(i18n::negotiate(&settings.http, request));
+            }
+        }))
+        .mount(api::MOUNT_POINT, api::routes())
+        .register(catchers![unauthorized])
+        .mount(
+            "/",
+            routes![
+                get_login,
+                post_login,
+                post_logout,
+                get_index,
+                get_dashboard,
+                get_comparison,
+                get_search,
+                get_settings,
+                get_sensors,
+                post_sensors,
+                get_sensor,
+                delete_sensor,
+                post_annotation,
+                delete_annotation,
+                get_sensor_json,
+                get_sensor_blob,
+                get_gallery,
+                get_sensor_history,
+                get_sensor_history_csv,
+                get_events,
+                get_metrics,
+                get_favicon,
+                get_favicon_16,
+                get_favicon_32,
+                get_apple_touch_icon,
+                get_android_chrome_192,
+                get_android_chrome_512,
+                get_bulma_css,
+                get_bulma_prefers_dark,
+                get_chart_js,
+                get_font_awesome,
+                get_webfonts_fa_solid_900,
+                get_webfonts_fa_regular_400,
+                get_webfonts_fa_brands_400,
+                get_sw_js,
+                get_webmanifest,
+            ],
+        ))
+}
+
+/// Response to unauthenticated requests.
+#[derive(Responder)]
+enum Unauthorized {
+    /// Browsers are redirected to the sign in page.
+    Redirect(Redirect),
+
+    /// API clients receive the plain `401 Unauthorized`.
+    #[response(status = 401)]
+    Api(&'static str),
+}
+
+#[catch(401)]
+fn unauthorized(request: &Request) -> Unauthorized {
+    if request.uri().path().starts_with(api::MOUNT_POINT) {
+        Unauthorized::Api("Unauthorized")
+    } else {
+        let base_path = request.guard::<Context>().succeeded().map(|context| context.base_path);
+        Unauthorized::Redirect(Redirect::to(format!(
+            "{}{}",
+            base_path.unwrap_or_default(),
+            uri!(get_login: _),
+        )))
+    }
+}
+
+#[derive(FromForm)]
+struct LoginForm {
+    name: String,
+    password: String,
+}
+
+#[get("/login?<failed>")]
+fn get_login(context: Context, failed: Option<bool>) -> Result<ToHtmlString<impl ToString>> {
+    Ok(ToHtmlString(templates::LoginTemplate {
+        context,
+        failed: failed.unwrap_or_default(),
+    }))
+}
+
+#[post("/login", data = "<form>")]
+fn post_login(
+    context: Context,
+    settings: State<Settings>,
+    db: State<Connection>,
+    mut cookies: Cookies,
+    form: Form<LoginForm>,
+) -> Result<Redirect> {
+    if auth::verify_password(&settings, &form.name, &form.password) {
+        info!("`{}` has signed in.", form.name);
+        auth::sign_in(&context, &db, &mut cookies, &form.name)?;
+        Ok(Redirect::to(context.uri(uri!(get_index))))
+    } else {
+        warn!(
+            "Failed sign in attempt for `{}` from {:?}.",
+            form.name, context.client_ip
+        );
+        Ok(Redirect::to(context.uri(uri!(get_login: true))))
+    }
+}
+
+#[post("/logout")]
+fn post_logout(context: Context, db: State<Connection>, mut cookies: Cookies) -> Result<Redirect> {
+    auth::sign_out(&context, &db, &mut cookies)?;
+    Ok(Redirect::to(context.uri(uri!(get_login: _))))
 }
 
 #[get("/")]
-fn get_index(db: State<Connection>) -> Result<ToHtmlString<impl ToString>> {
+fn get_index(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    settings: State<Settings>,
+) -> Result<ToHtmlString<impl ToString>> {
     let actuals = task::block_on(db.select_actuals())?
         .into_iter()
         .group_by(|(sensor, _)| sensor.location.clone())
         .into_iter()
         .map(|(location, group)| (location, group.collect_vec()))
         .collect_vec();
-    Ok(ToHtmlString(templates::IndexTemplate { actuals }))
+    let dashboards = settings
+        .dashboards
+        .iter()
+        .map(|(dashboard_id, dashboard)| {
+            let title = dashboard.title.clone().unwrap_or_else(|| dashboard_id.clone());
+            (dashboard_id.clone(), title)
+        })
+        .sorted()
+        .collect();
+    let push_services = settings
+        .services
+        .iter()
+        .filter_map(|(service_id, service)| match service {
+            Service::WebPush(service) => Some((service_id.clone(), service.public_key.clone())),
+            _ => None,
+        })
+        .sorted()
+        .collect();
+    Ok(ToHtmlString(templates::IndexTemplate {
+        context,
+        dashboards,
+        push_services,
+        actuals,
+    }))
+}
+
+#[get("/dashboards/<dashboard_id>")]
+fn get_dashboard(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    settings: State<Settings>,
+    dashboard_id: String,
+) -> Result<Option<ToHtmlString<impl ToString>>> {
+    let dashboard = match settings.dashboards.get(&dashboard_id) {
+        Some(dashboard) => dashboard,
+        None => return Ok(None),
+    };
+    Ok(Some(ToHtmlString(templates::DashboardTemplate {
+        context,
+        title: dashboard.title.clone().unwrap_or(dashboard_id),
+        sections: task::block_on(dashboard::load(&db, dashboard))?,
+    })))
+}
+
+/// Overlays the selected sensors on a single chart.
+#[get("/compare?<form..>")]
+fn get_comparison(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    form: Form<ComparisonForm>,
+) -> Result<ToHtmlString<impl ToString>> {
+    let form = form.into_inner();
+    let since = Local::now() - Duration::minutes(form.minutes);
+    let mut series = Vec::with_capacity(form.sensor_ids.len());
+    for sensor_id in form.sensor_ids.iter() {
+        if let Some((sensor, reading)) = task::block_on(db.select_sensor(sensor_id))? {
+            let readings = task::block_on(db.select_readings(sensor_id, &since))?;
+            series.push((sensor, reading, readings));
+        }
+    }
+    Ok(ToHtmlString(templates::ComparisonTemplate {
+        context,
+        actuals: task::block_on(db.select_actuals())?
+            .into_iter()
+            .filter(|(_, reading)| f64::try_from(&reading.value).is_ok())
+            .collect(),
+        sensor_ids: form.sensor_ids,
+        minutes: form.minutes,
+        chart: if series.is_empty() {
+            None
+        } else {
+            Some(templates::F64ChartPartialTemplate::new_comparison(&series).to_string())
+        },
+    }))
+}
+
+/// Searches the sensors by the text and the facets.
+#[get("/search?<query..>")]
+fn get_search(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    query: Form<SearchQuery>,
+) -> Result<ToHtmlString<impl ToString>> {
+    // The text is matched in the browser, so the page includes the sensors which don't match it, but hides them.
+    let summaries: Vec<_> = task::block_on(db.select_sensor_summaries())?
+        .into_iter()
+        .filter(|summary| query.matches_facets(summary))
+        .collect();
+    Ok(ToHtmlString(templates::SearchTemplate {
+        context,
+        facets: Facets::new(summaries.iter().filter(|summary| query.matches_text(&summary.sensor))),
+        query: query.into_inner(),
+        summaries,
+    }))
 }
 
 #[get("/settings")]
-fn get_settings(settings: State<Settings>) -> Result<ToHtmlString<impl ToString>> {
+fn get_settings(_admin: Admin, context: Context, settings: State<Settings>) -> Result<ToHtmlString<impl ToString>> {
     Ok(ToHtmlString(templates::SettingsTemplate {
+        context,
         settings: toml::to_string_pretty(&toml::Value::try_from(settings.inner())?)?,
     }))
 }
 
-#[get("/sensors/<sensor_id>?<minutes>")]
+#[get("/sensors")]
+fn get_sensors(_admin: Admin, context: Context, db: State<Connection>) -> Result<ToHtmlString<impl ToString>> {
+    Ok(ToHtmlString(templates::SensorsTemplate {
+        context,
+        summaries: task::block_on(db.select_sensor_summaries())?,
+    }))
+}
+
+#[post("/sensors", data = "<form>")]
+fn post_sensors(
+    _admin: Admin,
+    context: Context,
+    db: State<Connection>,
+    form: Form<BulkActionForm>,
+) -> Result<Redirect> {
+    let form = form.into_inner();
+    match form.action {
+        BulkAction::Delete => task::block_on(db.delete_sensors(&form.sensor_ids))?,
+        BulkAction::Hide => task::block_on(db.set_sensors_hidden(&form.sensor_ids, true))?,
+        BulkAction::Unhide => task::block_on(db.set_sensors_hidden(&form.sensor_ids, false))?,
+        BulkAction::DeleteReadingsOlderThan(days) => {
+            let before = Local::now() - Duration::days(days);
+            let deleted_count = task::block_on(db.delete_readings_older_than(&form.sensor_ids, &before))?;
+            info!("Deleted {} readings older than {}.", deleted_count, before);
+        }
+    }
+    Ok(Redirect::to(context.uri(uri!(get_sensors))))
+}
+
+#[get("/sensors/<sensor_id>?<range..>")]
 fn get_sensor<'r>(
+    _user: User,
+    context: Context,
     db: State<Connection>,
     if_none_match: Option<IfNoneMatch>,
     sensor_id: String,
-    minutes: Option<i64>,
+    range: StdResult<Form<Range>, String>,
 ) -> Result<Response<'r>> {
+    let range = match range {
+        Ok(range) => range.into_inner(),
+        Err(_) => return Response::build().status(Status::BadRequest).ok(),
+    };
     if let Some((sensor, reading)) = task::block_on(db.select_sensor(&sensor_id))? {
         if let Some(IfNoneMatch(entity_tag)) = if_none_match {
             if reading.entity_tag().weak_eq(&entity_tag) {
@@ -104,25 +378,30 @@ fn get_sensor<'r>(
             }
         }
 
-        let minutes = minutes.unwrap_or(60);
-        let readings = task::block_on(db.select_readings(&sensor_id, &(Local::now() - Duration::minutes(minutes))))?;
-        let chart = if readings.is_empty() {
-            // language=html
-            r#"<div class="notification content"><p>No data points within the period.</p></div>"#.to_string()
-        } else if TryInto::<f64>::try_into(&reading.value).is_ok() {
-            templates::F64ChartPartialTemplate::new(
-                &sensor.title(),
-                readings,
-                if let Value::Energy(_) = reading.value {
-                    WH_IN_JOULE
-                } else {
-                    1.0
-                },
-            )
+        let Range { since, until, .. } = range;
+        let readings = task::block_on(db.select_readings_between(&sensor_id, &since, &until))?;
+        let annotations = task::block_on(db.select_annotations(&sensor_id, &sensor.location, &since, &until))?;
+        let statistics = task::block_on(db.select_statistics(&sensor_id, &since, &until))?;
+        let chart = if TryInto::<f64>::try_into(&reading.value).is_ok() && !readings.is_empty() {
+            templates::F64ChartPartialTemplate::new(&sensor.title(), readings, reading.value.chart_multiplier())
+                .range(&range)
+                .annotations(&annotations)
+                .to_string()
+        } else if timeline::is_state(&reading.value) {
+            let initial = task::block_on(db.select_last_reading_before(&sensor_id, &since))?;
+            let bands = timeline::bands(initial.as_ref(), &readings, since, until);
+            templates::StateTimelinePartialTemplate {
+                duty_cycles: timeline::duty_cycles(&bands, since, until),
+                bands,
+            }
             .to_string()
+        } else if !readings.is_empty() {
+            let mut markers = timeline::markers(&readings, since, until);
+            markers.reverse();
+            templates::EventMarkersPartialTemplate { markers }.to_string()
         } else {
             // language=html
-            r#"<div class="notification content"><p>Chart is unimplemented for this sensor.</p></div>"#.to_string()
+            r#"<div class="notification content"><p>No data points within the period.</p></div>"#.to_string()
         };
 
         Response::build()
@@ -130,10 +409,13 @@ fn get_sensor<'r>(
             .header(ETag(reading.entity_tag()))
             .sized_body(Cursor::new(
                 templates::SensorTemplate {
+                    context,
                     sensor,
                     reading,
                     chart,
-                    minutes,
+                    range,
+                    statistics,
+                    annotations,
                     reading_count: task::block_on(db.select_sensor_reading_count(&sensor_id))?,
                 }
                 .to_string(),
@@ -145,18 +427,197 @@ fn get_sensor<'r>(
 }
 
 #[delete("/sensors/<sensor_id>")]
-fn delete_sensor(db: State<Connection>, sensor_id: String) -> Result<Redirect> {
+fn delete_sensor(_admin: Admin, context: Context, db: State<Connection>, sensor_id: String) -> Result<Redirect> {
     task::block_on(db.delete_sensor(&sensor_id))?;
-    Ok(Redirect::to(uri!(get_index)))
+    Ok(Redirect::to(context.uri(uri!(get_index))))
+}
+
+#[post("/sensors/<sensor_id>/annotations", data = "<form>")]
+fn post_annotation(
+    Admin(user): Admin,
+    context: Context,
+    db: State<Connection>,
+    sensor_id: String,
+    form: Form<AnnotationForm>,
+) -> Result<Option<Redirect>> {
+    let (sensor, _) = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some(actual) => actual,
+        None => return Ok(None),
+    };
+    let form = form.into_inner();
+    let range = form.range;
+    task::block_on(db.insert_annotation(&form.into_annotation(&sensor, user.name)))?;
+    Ok(Some(Redirect::to(context.uri(uri!(get_sensor: &sensor.id, range)))))
 }
 
+/// Deletes the annotation, which is shown on the sensor page.
+#[delete("/sensors/<sensor_id>/annotations/<annotation_id>", data = "<range>")]
+fn delete_annotation(
+    _admin: Admin,
+    context: Context,
+    db: State<Connection>,
+    sensor_id: String,
+    annotation_id: i64,
+    range: Form<Range>,
+) -> Result<Option<Redirect>> {
+    let (sensor, _) = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some(actual) => actual,
+        None => return Ok(None),
+    };
+    if !task::block_on(db.delete_sensor_annotation(annotation_id, &sensor.id, &sensor.location))? {
+        return Ok(None);
+    }
+    Ok(Some(Redirect::to(
+        context.uri(uri!(get_sensor: &sensor.id, range.into_inner())),
+    )))
+}
+
+/// Returns the latest sensor reading.
+///
+/// Kept for compatibility, see the versioned API in the [`api`] module.
 #[get("/sensors/<sensor_id>/json")]
-fn get_sensor_json(db: State<Connection>, sensor_id: String) -> Result<Option<Json<Reading>>> {
+fn get_sensor_json(_user: User, db: State<Connection>, sensor_id: String) -> Result<Option<Json<Reading>>> {
     // TODO: ETag
     // TODO: Cache-Control: private, no-cache
     Ok(task::block_on(db.select_sensor(&sensor_id))?.map(|(_, reading)| Json(reading)))
 }
 
+/// Returns the binary sensor value with the detected content type.
+///
+/// The latest reading is looked up first since non-logged readings are not stored in the history.
+#[get("/sensors/<sensor_id>/blob?<timestamp>")]
+fn get_sensor_blob(
+    _user: User,
+    db: State<Connection>,
+    sensor_id: String,
+    timestamp: i64,
+) -> Result<Option<Content<Vec<u8>>>> {
+    let reading = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some((_, reading)) if reading.timestamp.timestamp_millis() == timestamp => Some(reading),
+        _ => match Local.timestamp_millis_opt(timestamp).single() {
+            Some(timestamp) => task::block_on(db.select_reading(&sensor_id, &timestamp))?,
+            None => None,
+        },
+    };
+    Ok(match reading.map(|reading| reading.value) {
+        Some(Value::Blob(content)) => {
+            let content_type = ContentType::parse_flexible(sniff_mime_type(&content)).unwrap_or(ContentType::Binary);
+            Some(Content(content_type, content.to_vec()))
+        }
+        _ => None,
+    })
+}
+
+/// Shows the sensor's images and videos within the range.
+#[get("/sensors/<sensor_id>/gallery?<range..>")]
+fn get_gallery(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    sensor_id: String,
+    range: Form<Range>,
+) -> Result<Option<ToHtmlString<impl ToString>>> {
+    let sensor = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some((sensor, _)) => sensor,
+        None => return Ok(None),
+    };
+    let range = range.into_inner();
+    let readings = task::block_on(db.select_media_readings_between(&sensor_id, &range.since, &range.until))?;
+    Ok(Some(ToHtmlString(templates::GalleryTemplate {
+        context,
+        sensor,
+        readings,
+        range,
+    })))
+}
+
+/// Shows the sensor readings within the range as a paged table.
+#[get("/sensors/<sensor_id>/history?<query..>")]
+fn get_sensor_history(
+    _user: User,
+    context: Context,
+    db: State<Connection>,
+    sensor_id: String,
+    query: Form<HistoryQuery>,
+) -> Result<Option<ToHtmlString<impl ToString>>> {
+    let sensor = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some((sensor, _)) => sensor,
+        None => return Ok(None),
+    };
+    let query = query.into_inner();
+    let reading_query = query.reading_query();
+    Ok(Some(ToHtmlString(templates::HistoryTemplate {
+        context,
+        sensor,
+        reading_count: task::block_on(db.select_reading_count_by(&sensor_id, &reading_query))? as usize,
+        readings: task::block_on(db.select_readings_by(
+            &sensor_id,
+            &reading_query,
+            Some(history::PAGE_SIZE as i64),
+            query.offset(),
+        ))?,
+        query,
+    })))
+}
+
+/// Downloads the filtered sensor readings within the range as CSV.
+#[get("/sensors/<sensor_id>/history.csv?<query..>")]
+fn get_sensor_history_csv<'r>(
+    _user: User,
+    db: State<Connection>,
+    sensor_id: String,
+    query: Form<HistoryQuery>,
+) -> Result<Option<Response<'r>>> {
+    if task::block_on(db.select_sensor(&sensor_id))?.is_none() {
+        return Ok(None);
+    }
+    let readings = task::block_on(db.select_readings_by(&sensor_id, &query.reading_query(), None, 0))?;
+    let file_name: String = sensor_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    Ok(Some(
+        Response::build()
+            .header(ContentType::CSV)
+            .raw_header(
+                "Content-Disposition",
+                format!(r#"attachment; filename="{}.csv""#, file_name),
+            )
+            .sized_body(Cursor::new(history::to_csv(&readings)))
+            .finalize(),
+    ))
+}
+
+/// Exposes the numeric sensor values and the process metrics to [Prometheus](https://prometheus.io/).
+#[get("/metrics")]
+fn get_metrics(_user: User, db: State<Connection>, settings: State<Settings>) -> Result<Content<String>> {
+    Ok(Content(
+        ContentType::Plain,
+        metrics::render(
+            &settings.http.metrics,
+            &task::block_on(db.select_actuals())?,
+            &process_metrics::snapshot(),
+            task::block_on(db.select_size())?,
+        ),
+    ))
+}
+
+/// Streams the bus messages, optionally filtered by the sensor ID pattern, as server-sent events.
+///
+/// Note that each connected client occupies a worker thread, see `http.workers` setting.
+#[get("/events?<pattern>")]
+fn get_events<'r>(_user: User, broadcaster: State<Broadcaster>, pattern: Option<String>) -> Result<Response<'r>> {
+    let pattern = match pattern.as_deref().map(Regex::new).transpose() {
+        Ok(pattern) => pattern,
+        Err(_) => return Response::build().status(Status::BadRequest).ok(),
+    };
+    Response::build()
+        .raw_header("Content-Type", "text/event-stream")
+        .raw_header("Cache-Control", "no-cache")
+        .streamed_body(EventStream::new(task::block_on(broadcaster.subscribe()), pattern))
+        .ok()
+}
+
 #[get("/favicon.ico")]
 fn get_favicon() -> Cached {
     Cached(
@@ -281,18 +742,35 @@ fn get_sw_js() -> Cached {
     )
 }
 
+/// Web app manifest with the favourite sensors as the app shortcuts.
+///
+/// The shortcuts are only included for the signed in users, since browsers fetch the manifest on the login page too.
 #[get("/my-iot.webmanifest")]
-fn get_webmanifest() -> Cached {
-    Cached(
-        STATIC_MAX_AGE_SECS,
-        Content(ContentType::JSON, include_bytes!("statics/my-iot.webmanifest")),
-    )
+fn get_webmanifest(user: Option<User>, db: State<Connection>, settings: State<Settings>) -> Result<Content<String>> {
+    let mut manifest: serde_json::Value = serde_json::from_slice(include_bytes!("statics/my-iot.webmanifest"))?;
+    if user.is_some() {
+        let mut shortcuts = Vec::new();
+        for sensor_id in settings.http.favourites.iter() {
+            if let Some((sensor, _)) = task::block_on(db.select_sensor(sensor_id))? {
+                shortcuts.push(serde_json::json!({
+                    "name": format!("{} – {}", sensor.title(), sensor.location),
+                    "short_name": sensor.title(),
+                    "url": format!("sensors/{}", Uri::percent_encode(&sensor.id)),
+                    "icons": [{"src": "static/android-chrome-192x192.png", "sizes": "192x192"}],
+                }));
+            }
+        }
+        manifest["shortcuts"] = shortcuts.into();
+    }
+    Ok(Content(ContentType::JSON, manifest.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
+    use rocket::http::Header;
     use rocket::local::Client;
 
+    use crate::core::db::annotation::Annotation;
     use crate::settings::*;
 
     use super::*;
@@ -315,6 +793,254 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn sensors_ok() -> Result {
+        let client = client().await?;
+        let response = client.get("/sensors").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn dashboard_ok() -> Result {
+        let settings = r#"
+            [[dashboards.home.sections]]
+            title = "Living room"
+
+            [[dashboards.home.sections.tiles]]
+            sensor_id = "missing"
+            widget = "Gauge"
+        "#;
+        let client = client_with_settings(settings).await?.0;
+        let response = client.get("/dashboards/home").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn missing_dashboard_not_found() -> Result {
+        let client = client().await?;
+        let response = client.get("/dashboards/missing").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn comparison_ok() -> Result {
+        let client = client().await?;
+        let response = client.get("/compare?sensor_id=a&sensor_id=b&minutes=60").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn search_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test::temperature").value(Value::Temperature(21.5)))
+            .await?;
+        db.upsert_message(&Message::new("kitchen::temperature").value(Value::Temperature(20.0)))
+            .await?;
+        let mut response = client.get("/search?q=TEST&value_type=Temperature").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        // The sensors not matching the text are rendered, but hidden, so that the browser filters them.
+        let body = response.body_string().unwrap();
+        assert!(body.contains(r#"<span id="search-count">1</span>"#));
+        assert!(body.contains(r#"data-search-text="kitchen::temperature"#));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_search_ok() -> Result {
+        let client = client().await?;
+        let response = client.get("/api/v1/search?q=test&prefix=test%3A%3A").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sensor_range_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::Temperature(21.5)))
+            .await?;
+        for query in &[
+            "minutes=5",
+            "period=week",
+            "from=2020-12-01T10%3A00&to=2020-12-01T11%3A30",
+        ] {
+            let response = client.get(format!("/sensors/test?{}", query)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        }
+        let mut response = client.get("/sensors/test").dispatch();
+        assert!(response
+            .body_string()
+            .unwrap()
+            .contains(r#"href="sensors/test?minutes=10""#));
+        let response = client.get("/sensors/test?from=3000&to=1000").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sensor_blob_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        let content = Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]);
+        let message = Message::new("test::snapshot").value(Value::Blob(Arc::new(content)));
+        db.upsert_message(&message).await?;
+
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let timestamp = message.reading.timestamp.timestamp_millis();
+        let mut response = client
+            .get(format!("/sensors/test::snapshot/blob?timestamp={}", timestamp))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JPEG));
+        assert_eq!(response.body_bytes().unwrap(), vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00]);
+
+        let response = client
+            .get(format!("/sensors/test::snapshot/blob?timestamp={}", timestamp + 1))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client
+            .get(format!("/sensors/test::snapshot/blob?timestamp={}", i64::MAX))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn gallery_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test::snapshot").value(Value::ImageUrl("https://example.com/1.jpg".into())))
+            .await?;
+        let content = Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]);
+        let message = Message::new("test::snapshot")
+            .value(Value::Blob(Arc::new(content)))
+            .timestamp(Local::now() - chrono::Duration::seconds(1));
+        db.upsert_message(&message).await?;
+        let mut response = client.get("/sensors/test::snapshot/gallery?period=day").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.body_string().unwrap();
+        assert!(body.contains("https://example.com/1.jpg"));
+        assert!(body.contains("/blob?timestamp="));
+        let response = client.get("/sensors/missing/gallery").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sensor_history_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::StringEnum("heating".into())))
+            .await?;
+        let mut response = client
+            .get("/sensors/test/history?period=day&sort=value&page=1")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body_string().unwrap().contains("heating"));
+        let response = client.get("/sensors/missing/history").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sensor_history_csv_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        for (minutes, value) in &[(2, 100.0), (1, 5.0)] {
+            let message = Message::new("test::power")
+                .value(Value::Power(*value))
+                .timestamp(Local::now() - Duration::minutes(*minutes));
+            db.upsert_message(&message).await?;
+        }
+        let mut response = client.get("/sensors/test::power/history.csv?min=10").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+        let body = response.body_string().unwrap();
+        assert!(body.contains(",Power,100\r\n"));
+        assert!(!body.contains(",Power,5\r\n"));
+        let response = client.get("/sensors/missing/history.csv").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn metrics_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::Temperature(21.5)))
+            .await?;
+        let mut response = client.get("/metrics").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.body_string().unwrap().contains(r#"sensor_id="test""#));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn webmanifest_shortcuts_ok() -> Result {
+        let (client, _) = client_with_settings(
+            r#"
+                [http]
+                favourites = ["test::temperature", "missing"]
+            "#,
+        )
+        .await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test::temperature").value(Value::Temperature(21.5)))
+            .await?;
+        let mut response = client.get("/my-iot.webmanifest").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let manifest: serde_json::Value = serde_json::from_str(&response.body_string().unwrap())?;
+        assert_eq!(manifest["shortcuts"][0]["url"], "sensors/test::temperature");
+        assert_eq!(manifest["shortcuts"].as_array().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_push_subscription_ok() -> Result {
+        let (client, _) = client_with_settings(
+            r#"
+                [services.push]
+                type = "WebPush"
+                subject = "mailto:admin@example.com"
+                public_key = "key"
+
+                [services.push.secrets]
+                private_key = "secret"
+            "#,
+        )
+        .await?;
+        let body = r#"{"endpoint": "https://push.example.com/1", "keys": {"p256dh": "key", "auth": "secret"}}"#;
+        let response = client
+            .post("/api/v1/push/push/subscriptions")
+            .header(ContentType::JSON)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let db = client.rocket().state::<Connection>().unwrap();
+        assert_eq!(db.select_push_subscriptions("push").await?.len(), 1);
+
+        let response = client
+            .post("/api/v1/push/missing/subscriptions")
+            .header(ContentType::JSON)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
     #[async_std::test]
     async fn favicon_ok() -> Result {
         let client = client().await?;
@@ -324,10 +1050,332 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn api_sensors_ok() -> Result {
+        let client = client().await?;
+        let response = client.get("/api/v1/sensors").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_out_of_range_bad_request() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::Temperature(21.5)))
+            .await?;
+        for url in &[
+            format!("/api/v1/sensors/test/readings?from={}", i64::MAX),
+            format!("/api/v1/sensors/test/readings?interval={}", i64::MAX),
+            format!("/api/v1/sensors/test/statistics?to={}", i64::MIN),
+            format!("/api/v1/sensors/test/annotations?from={}", i64::MAX),
+        ] {
+            let response = client.get(url).dispatch();
+            assert_eq!(response.status(), Status::BadRequest, "{}", url);
+        }
+        let response = client
+            .post("/api/v1/readings")
+            .header(ContentType::JSON)
+            .body(format!(
+                r#"{{"sensor_id": "test", "value": "None", "timestamp": {}}}"#,
+                i64::MAX
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_post_reading_ok() -> Result {
+        let (client, mut rx) = client_and_receiver().await?;
+        let response = client
+            .post("/api/v1/readings")
+            .header(ContentType::JSON)
+            .body(r#"{"sensor_id": "test", "value": {"Temperature": 21.5}, "location": "Kitchen"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Accepted);
+
+        let message = rx.next().await.unwrap();
+        assert_eq!(message.sensor.id, "test");
+        assert_eq!(message.sensor.location, "Kitchen");
+        assert_eq!(message.reading.value, Value::Temperature(21.5));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_annotations_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").location("Home").value(Value::Temperature(21.5)))
+            .await?;
+
+        let response = client
+            .post("/api/v1/annotations")
+            .header(ContentType::JSON)
+            .body(r#"{"text": "Holiday", "location": "Home"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+
+        let mut response = client.get("/api/v1/sensors/test/annotations").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let annotations: serde_json::Value = serde_json::from_str(&response.body_string().unwrap())?;
+        assert_eq!(annotations[0]["text"], "Holiday");
+
+        let id = annotations[0]["id"].as_i64().unwrap();
+        let response = client.delete(format!("/api/v1/annotations/{}", id)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let response = client.delete(format!("/api/v1/annotations/{}", id)).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_annotation_ok() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::Temperature(21.5)))
+            .await?;
+        let response = client
+            .post("/sensors/test/annotations")
+            .header(ContentType::Form)
+            .body(format!(
+                "text=Replaced+the+battery&since={}",
+                Local::now().timestamp_millis()
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+
+        let mut response = client.get("/sensors/test").dispatch();
+        assert!(response.body_string().unwrap().contains("Replaced the battery"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_write_to_missing_sensor_not_found() -> Result {
+        let client = client().await?;
+        let response = client
+            .post("/api/v1/sensors/missing/write")
+            .header(ContentType::JSON)
+            .body(r#"{"value": {"Boolean": true}}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_openapi_ok() -> Result {
+        let client = client().await?;
+        let response = client.get("/api/v1/openapi.json").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn unauthenticated_index_redirects_to_login() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+        assert_eq!(response.headers().get_one("Location"), Some("/login"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn unauthenticated_api_unauthorized() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client.get("/api/v1/sensors").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn api_token_ok() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client
+            .get("/api/v1/sensors")
+            .header(Header::new("Authorization", "Bearer viewer-token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_only_settings_forbidden() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client
+            .get("/settings")
+            .header(Header::new("Authorization", "Bearer viewer-token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_only_annotation_forbidden() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client
+            .post("/api/v1/annotations")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer viewer-token"))
+            .body(r#"{"text": "Holiday"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .delete("/api/v1/annotations/1")
+            .header(Header::new("Authorization", "Bearer viewer-token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn delete_other_sensor_annotation_not_found() -> Result {
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&Message::new("test").value(Value::Temperature(21.5)))
+            .await?;
+        let mut annotation = Annotation::new("Replaced the battery", Local::now());
+        annotation.sensor_id = Some("other".into());
+        let id = db.insert_annotation(&annotation).await?;
+        let response = client
+            .delete(format!("/sensors/test/annotations/{}", id))
+            .header(ContentType::Form)
+            .body("period=day")
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn read_only_write_control_hidden() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let db = client.rocket().state::<Connection>().unwrap();
+        let mut message = Message::new("test").value(Value::Boolean(true));
+        message.sensor.is_writable = true;
+        db.upsert_message(&message).await?;
+        for url in &["/", "/sensors/test"] {
+            let mut response = client
+                .get(*url)
+                .header(Header::new("Authorization", "Bearer viewer-token"))
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            assert!(!response.body_string().unwrap().contains("data-write-sensor-id"));
+        }
+
+        let client = client().await?;
+        let db = client.rocket().state::<Connection>().unwrap();
+        db.upsert_message(&message).await?;
+        let mut response = client.get("/sensors/test").dispatch();
+        assert!(response.body_string().unwrap().contains("data-write-sensor-id"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn login_ok() -> Result {
+        let client = client_with_settings(&users()?).await?.0;
+        let response = client
+            .post("/login")
+            .header(ContentType::Form)
+            .body("name=viewer&password=secret")
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+        assert_eq!(response.headers().get_one("Location"), Some("/"));
+
+        // The client keeps the session cookie.
+        let response = client.get("/").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn base_path_redirect_ok() -> Result {
+        let settings = format!("{}\n[http]\nbase_path = \"/iot/\"\n", users()?);
+        let client = client_with_settings(&settings).await?.0;
+        let response = client.get("/").dispatch();
+        assert_eq!(response.headers().get_one("Location"), Some("/iot/login"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn forwarded_prefix_ok() -> Result {
+        let settings = format!("{}\n[http]\ntrust_forwarded_headers = true\n", users()?);
+        let client = client_with_settings(&settings).await?.0;
+        let mut response = client
+            .get("/login")
+            .header(Header::new("X-Forwarded-Prefix", "/iot"))
+            .dispatch();
+        assert!(response.body_string().unwrap().contains(r#"<base href="/iot/">"#));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn untrusted_forwarded_prefix_ignored() -> Result {
+        let client = client().await?;
+        let mut response = client
+            .get("/login")
+            .header(Header::new("X-Forwarded-Prefix", "/iot"))
+            .dispatch();
+        assert!(response.body_string().unwrap().contains(r#"<base href="/">"#));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn accept_language_ok() -> Result {
+        let client = client().await?;
+        let mut response = client
+            .get("/login")
+            .header(Header::new("Accept-Language", "nl-NL,nl;q=0.9,en;q=0.8"))
+            .dispatch();
+        let body = response.body_string().unwrap();
+        assert!(body.contains(r#"<html lang="nl">"#));
+        assert!(body.contains("Wachtwoord"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn locale_setting_ok() -> Result {
+        let client = client_with_settings("[http]\nlocale = \"ru\"\n").await?.0;
+        let mut response = client
+            .get("/login")
+            .header(Header::new("Accept-Language", "nl"))
+            .dispatch();
+        assert!(response.body_string().unwrap().contains("Пароль"));
+        Ok(())
+    }
+
+    /// Defines a read-only user with the `secret` password.
+    fn users() -> crate::Result<String> {
+        Ok(format!(
+            r#"
+                [http.users.viewer]
+                role = "ReadOnly"
+
+                [http.users.viewer.secrets]
+                password_hash = "{}"
+                api_tokens = ["viewer-token"]
+            "#,
+            hash_password("secret")?,
+        ))
+    }
+
     async fn client() -> crate::Result<Client> {
-        Ok(Client::new(make_rocket(
-            &toml::from_str::<Settings>("")?,
+        Ok(client_and_receiver().await?.0)
+    }
+
+    /// Builds the test client and returns the receiver for the messages sent by the web server.
+    async fn client_and_receiver() -> crate::Result<(Client, Receiver)> {
+        client_with_settings("").await
+    }
+
+    async fn client_with_settings(settings: &str) -> crate::Result<(Client, Receiver)> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let (_, bus_rx) = futures::channel::mpsc::unbounded();
+        let client = Client::new(make_rocket(
+            &toml::from_str::<Settings>(settings)?,
             Connection::open(":memory:").await?,
-        )?)?)
+            tx,
+            bus_rx,
+        )?)?;
+        Ok((client, rx))
     }
 }
@@ -0,0 +1,116 @@
+use rocket::request::{FormItems, FromForm};
+
+use crate::core::db::annotation::Annotation;
+use crate::prelude::*;
+use crate::web::range::{parse_timestamp, Range};
+
+/// What the annotation is attached to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnnotationScope {
+    Sensor,
+    Location,
+    Everything,
+}
+
+/// Sensor page annotation form.
+///
+/// Implemented manually because the form also carries the currently displayed range as `from` and `to`,
+/// so that the user gets back to the same chart.
+#[derive(PartialEq, Debug)]
+pub struct AnnotationForm {
+    pub text: String,
+    pub scope: AnnotationScope,
+    pub since: DateTime<Local>,
+
+    /// Inclusive, defaults to `since`.
+    pub until: DateTime<Local>,
+
+    /// The displayed sensor page range.
+    pub range: Range,
+}
+
+impl AnnotationForm {
+    pub fn into_annotation(self, sensor: &Sensor, author: Option<String>) -> Annotation {
+        let mut annotation = Annotation::new(self.text, self.since);
+        annotation.until = self.until;
+        annotation.author = author;
+        match self.scope {
+            AnnotationScope::Sensor => annotation.sensor_id = Some(sensor.id.clone()),
+            AnnotationScope::Location => annotation.location = Some(sensor.location.clone()),
+            AnnotationScope::Everything => {}
+        }
+        annotation
+    }
+}
+
+impl<'f> FromForm<'f> for AnnotationForm {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> StdResult<Self, Self::Error> {
+        let items: Vec<(String, String)> = items.map(|item| item.key_value_decoded()).collect();
+        let range = Range::from_items(items.iter().cloned())?;
+        let mut text = None;
+        let mut scope = AnnotationScope::Sensor;
+        let mut since = None;
+        let mut until = None;
+
+        for (key, value) in items {
+            match key.as_str() {
+                "text" if !value.trim().is_empty() => text = Some(value.trim().to_string()),
+                "scope" => {
+                    scope = match value.as_str() {
+                        "sensor" => AnnotationScope::Sensor,
+                        "location" => AnnotationScope::Location,
+                        "everything" => AnnotationScope::Everything,
+                        _ => return Err(format!("unknown scope: `{}`", value)),
+                    }
+                }
+                "since" if !value.is_empty() => since = Some(parse_timestamp(&value)?),
+                "until" if !value.is_empty() => until = Some(parse_timestamp(&value)?),
+                _ => {}
+            }
+        }
+
+        let since = since.ok_or_else(|| "`since` is required".to_string())?;
+        let until = until.unwrap_or(since);
+        if until < since {
+            return Err("`since` must not be after `until`".into());
+        }
+        Ok(Self {
+            text: text.ok_or_else(|| "`text` is required".to_string())?,
+            scope,
+            since,
+            until,
+            range,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_annotation_ok() -> Result {
+        let form = AnnotationForm::from_form(
+            &mut FormItems::from("text=+Holiday+&scope=location&since=1000&until=&from=0&to=5000"),
+            true,
+        )
+        .map_err(|error| anyhow!(error))?;
+        assert_eq!(form.range.until, Local.timestamp_millis(5000));
+
+        let sensor = Message::new("test").location("Home").sensor;
+        let annotation = form.into_annotation(&sensor, Some("alice".into()));
+        assert_eq!(annotation.text, "Holiday");
+        assert_eq!(annotation.sensor_id, None);
+        assert_eq!(annotation.location.as_deref(), Some("Home"));
+        assert!(annotation.is_point());
+        Ok(())
+    }
+
+    #[test]
+    fn inverted_range_err() {
+        let form = AnnotationForm::from_form(&mut FormItems::from("text=Oops&since=2000&until=1000"), true);
+        assert!(form.is_err());
+    }
+}
@@ -0,0 +1,481 @@
+//! Versioned JSON API.
+
+use chrono::Duration;
+use itertools::Itertools;
+use rocket::http::{ContentType, Status};
+use rocket::request::Form;
+use rocket::response::content::Content;
+use rocket::response::status::Custom;
+use rocket::{delete, get, post, routes, FromFormValue, Route, State};
+use rocket_contrib::json::Json;
+
+use crate::core::db::annotation::Annotation;
+use crate::core::db::push_subscription::PushSubscription;
+use crate::core::db::sensor_summary::SensorSummary;
+use crate::core::db::statistics::Statistics;
+use crate::prelude::*;
+use crate::settings::{Service, Settings};
+use crate::web::auth::{Admin, User};
+use crate::web::cached_content::Cached;
+use crate::web::search::{Facets, SearchQuery};
+
+pub const MOUNT_POINT: &str = "/api/v1";
+
+/// Period used when neither `from` nor `to` is specified.
+const DEFAULT_PERIOD_MINUTES: i64 = 60;
+
+/// Maximum number of the distinct values returned by `/sensors/<sensor_id>/values`.
+const MAX_DISTINCT_VALUES: i64 = 100;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        get_sensors,
+        get_search,
+        get_sensor,
+        get_readings,
+        get_values,
+        get_statistics,
+        get_annotations,
+        post_annotation,
+        delete_annotation,
+        post_reading,
+        post_write,
+        post_push_subscription,
+        get_openapi,
+    ]
+}
+
+/// Sensor representation with the latest reading and the storage metadata.
+#[derive(Serialize)]
+pub struct SensorJson {
+    pub id: String,
+    pub title: Option<String>,
+    pub location: String,
+    pub is_writable: bool,
+    pub is_hidden: bool,
+    pub value_type: &'static str,
+    pub reading_count: i64,
+    pub reading: Reading,
+}
+
+/// Found sensors along with the facets.
+#[derive(Serialize)]
+pub struct SearchResultsJson {
+    pub sensors: Vec<SensorJson>,
+    pub facets: Facets,
+}
+
+/// Aggregation function applied to readings within each interval.
+#[derive(FromFormValue, Clone, Copy, PartialEq, Debug)]
+pub enum Aggregate {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+/// Aggregated readings within a single interval.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Bucket {
+    /// The interval start.
+    pub timestamp: DateTime<Local>,
+
+    pub value: f64,
+
+    /// Number of the aggregated readings.
+    pub count: usize,
+}
+
+/// Body of `POST /readings`.
+#[derive(Deserialize)]
+pub struct NewReading {
+    pub sensor_id: String,
+    pub value: Value,
+
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub location: Option<String>,
+
+    /// Unix time in milliseconds, defaults to the current time.
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+
+    #[serde(rename = "type", default = "default_message_type")]
+    pub type_: MessageType,
+}
+
+/// Body of `POST /annotations`.
+#[derive(Deserialize)]
+pub struct NewAnnotation {
+    pub text: String,
+
+    /// Sensor to attach the annotation to.
+    #[serde(default)]
+    pub sensor_id: Option<String>,
+
+    /// Location to attach the annotation to.
+    #[serde(default)]
+    pub location: Option<String>,
+
+    /// Unix time in milliseconds, defaults to the current time.
+    #[serde(default)]
+    pub since: Option<i64>,
+
+    /// Inclusive Unix time in milliseconds, defaults to `since`.
+    #[serde(default)]
+    pub until: Option<i64>,
+}
+
+/// Body of `POST /sensors/<sensor_id>/write`.
+#[derive(Deserialize)]
+pub struct NewWrite {
+    pub value: Value,
+}
+
+fn default_message_type() -> MessageType {
+    MessageType::ReadLogged
+}
+
+impl From<SensorSummary> for SensorJson {
+    fn from(summary: SensorSummary) -> Self {
+        Self {
+            value_type: summary.reading.value.type_name(),
+            id: summary.sensor.id,
+            title: summary.sensor.title,
+            location: summary.sensor.location,
+            is_writable: summary.sensor.is_writable,
+            is_hidden: summary.is_hidden,
+            reading_count: summary.reading_count,
+            reading: summary.reading,
+        }
+    }
+}
+
+/// Lists the sensors, optionally filtered by the text, the location, the sensor ID prefix and the value type.
+#[get("/sensors?<query..>")]
+fn get_sensors(_user: User, db: State<Connection>, query: Form<SearchQuery>) -> Result<Json<Vec<SensorJson>>> {
+    Ok(Json(
+        task::block_on(db.select_sensor_summaries())?
+            .into_iter()
+            .filter(|summary| query.matches(summary))
+            .map(SensorJson::from)
+            .collect(),
+    ))
+}
+
+/// Searches the sensors and counts the found ones per location, service prefix and value type.
+#[get("/search?<query..>")]
+fn get_search(_user: User, db: State<Connection>, query: Form<SearchQuery>) -> Result<Json<SearchResultsJson>> {
+    let summaries: Vec<SensorSummary> = task::block_on(db.select_sensor_summaries())?
+        .into_iter()
+        .filter(|summary| query.matches(summary))
+        .collect();
+    Ok(Json(SearchResultsJson {
+        facets: Facets::new(summaries.iter()),
+        sensors: summaries.into_iter().map(SensorJson::from).collect(),
+    }))
+}
+
+#[get("/sensors/<sensor_id>")]
+fn get_sensor(_user: User, db: State<Connection>, sensor_id: String) -> Result<Option<Json<SensorJson>>> {
+    Ok(task::block_on(db.select_sensor_summary(&sensor_id))?.map(|summary| Json(summary.into())))
+}
+
+/// Returns the sensor readings within the `[from, to)` range, specified in Unix time milliseconds.
+///
+/// If `interval` (in seconds) is specified, the numeric readings are aggregated within each interval.
+#[get("/sensors/<sensor_id>/readings?<from>&<to>&<interval>&<aggregate>")]
+fn get_readings(
+    _user: User,
+    db: State<Connection>,
+    sensor_id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval: Option<i64>,
+    aggregate: Option<Aggregate>,
+) -> Result<StdResult<Json<serde_json::Value>, Status>> {
+    let (from, to) = match parse_range(from, to) {
+        Some(range) => range,
+        None => return Ok(Err(Status::BadRequest)),
+    };
+    let readings = task::block_on(db.select_readings_between(&sensor_id, &from, &to))?;
+
+    Ok(Ok(Json(match interval {
+        // Checked, since a huge interval would overflow the duration.
+        Some(interval) => match interval.checked_mul(1000).filter(|millis| *millis > 0) {
+            Some(millis) => serde_json::to_value(aggregate_readings(
+                &readings,
+                Duration::milliseconds(millis),
+                aggregate.unwrap_or(Aggregate::Avg),
+            ))?,
+            None => return Ok(Err(Status::BadRequest)),
+        },
+        None => serde_json::to_value(readings)?,
+    })))
+}
+
+/// Returns the statistics of the numeric readings within the `[from, to)` range, specified in Unix time milliseconds.
+#[get("/sensors/<sensor_id>/statistics?<from>&<to>")]
+fn get_statistics(
+    _user: User,
+    db: State<Connection>,
+    sensor_id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<StdResult<Json<Statistics>, Status>> {
+    let (from, to) = match parse_range(from, to) {
+        Some(range) => range,
+        None => return Ok(Err(Status::BadRequest)),
+    };
+    Ok(task::block_on(db.select_statistics(&sensor_id, &from, &to))?
+        .map(Json)
+        .ok_or(Status::NotFound))
+}
+
+/// Returns the annotations of the sensor, of its location and the global ones within the `[from, to)` range.
+#[get("/sensors/<sensor_id>/annotations?<from>&<to>")]
+fn get_annotations(
+    _user: User,
+    db: State<Connection>,
+    sensor_id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<StdResult<Json<Vec<Annotation>>, Status>> {
+    let (sensor, _) = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some(actual) => actual,
+        None => return Ok(Err(Status::NotFound)),
+    };
+    let (from, to) = match parse_range(from, to) {
+        Some(range) => range,
+        None => return Ok(Err(Status::BadRequest)),
+    };
+    Ok(Ok(Json(task::block_on(db.select_annotations(
+        &sensor.id,
+        &sensor.location,
+        &from,
+        &to,
+    ))?)))
+}
+
+/// Stores the annotation signed by the current user.
+#[post("/annotations", format = "json", data = "<annotation>")]
+fn post_annotation(
+    Admin(user): Admin,
+    db: State<Connection>,
+    annotation: Json<NewAnnotation>,
+) -> Result<StdResult<Custom<Json<Annotation>>, Status>> {
+    let new_annotation = annotation.into_inner();
+    let since = match new_annotation.since.map(from_millis) {
+        Some(Some(since)) => since,
+        Some(None) => return Ok(Err(Status::BadRequest)),
+        None => Local::now(),
+    };
+    let until = match new_annotation.until.map(from_millis) {
+        Some(Some(until)) => until,
+        Some(None) => return Ok(Err(Status::BadRequest)),
+        None => since,
+    };
+    if new_annotation.text.trim().is_empty() || until < since {
+        return Ok(Err(Status::UnprocessableEntity));
+    }
+    let mut annotation = Annotation::new(new_annotation.text, since);
+    annotation.until = until;
+    annotation.sensor_id = new_annotation.sensor_id;
+    annotation.location = new_annotation.location;
+    annotation.author = user.name;
+    annotation.id = task::block_on(db.insert_annotation(&annotation))?;
+    Ok(Ok(Custom(Status::Created, Json(annotation))))
+}
+
+#[delete("/annotations/<annotation_id>")]
+fn delete_annotation(_admin: Admin, db: State<Connection>, annotation_id: i64) -> Result<Status> {
+    Ok(if task::block_on(db.delete_annotation(annotation_id))? {
+        Status::NoContent
+    } else {
+        Status::NotFound
+    })
+}
+
+/// Returns the distinct sensor values, which are used to populate `StringEnum` controls.
+#[get("/sensors/<sensor_id>/values")]
+fn get_values(_user: User, db: State<Connection>, sensor_id: String) -> Result<Json<Vec<Value>>> {
+    Ok(Json(task::block_on(
+        db.select_distinct_values(&sensor_id, MAX_DISTINCT_VALUES),
+    )?))
+}
+
+/// Sends the reading to the message bus, so that external scripts could push their data.
+#[post("/readings", format = "json", data = "<reading>")]
+fn post_reading(_admin: Admin, tx: State<Sender>, reading: Json<NewReading>) -> Status {
+    let reading = reading.into_inner();
+    let message = Message::new(reading.sensor_id)
+        .type_(reading.type_)
+        .value(reading.value)
+        .optional_sensor_title(reading.title)
+        .optional_location(reading.location);
+    let message = match reading.timestamp {
+        Some(timestamp) => match from_millis(timestamp) {
+            Some(timestamp) => message.timestamp(timestamp),
+            None => return Status::BadRequest,
+        },
+        None => message,
+    };
+    task::block_on(message.send_to(&mut tx.inner().clone()));
+    Status::Accepted
+}
+
+/// Sends the `Write` message to the writable sensor.
+#[post("/sensors/<sensor_id>/write", format = "json", data = "<write>")]
+fn post_write(
+    _admin: Admin,
+    db: State<Connection>,
+    tx: State<Sender>,
+    sensor_id: String,
+    write: Json<NewWrite>,
+) -> Result<Status> {
+    let (sensor, _) = match task::block_on(db.select_sensor(&sensor_id))? {
+        Some(actual) => actual,
+        None => return Ok(Status::NotFound),
+    };
+    if !sensor.is_writable {
+        return Ok(Status::UnprocessableEntity);
+    }
+    let message = Message::new(sensor.id)
+        .type_(MessageType::Write)
+        .value(write.into_inner().value)
+        .optional_sensor_title(sensor.title)
+        .location(sensor.location);
+    task::block_on(message.send_to(&mut tx.inner().clone()));
+    Ok(Status::Accepted)
+}
+
+/// Subscribes the browser to the notifications of the `WebPush` service.
+#[post("/push/<service_id>/subscriptions", data = "<subscription>")]
+fn post_push_subscription(
+    _user: User,
+    db: State<Connection>,
+    settings: State<Settings>,
+    service_id: String,
+    subscription: Json<PushSubscription>,
+) -> Result<Option<Status>> {
+    match settings.services.get(&service_id) {
+        Some(Service::WebPush(_)) => {
+            task::block_on(db.upsert_push_subscription(&service_id, &subscription))?;
+            Ok(Some(Status::Created))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// [OpenAPI](https://swagger.io/specification/) description of the API.
+#[get("/openapi.json")]
+fn get_openapi() -> Cached {
+    Cached(
+        crate::web::STATIC_MAX_AGE_SECS,
+        Content(ContentType::JSON, include_bytes!("../statics/openapi.json")),
+    )
+}
+
+/// Converts the optional Unix time milliseconds range into the timestamps, defaulting to the last hour.
+///
+/// Returns `None` if any of the timestamps is out of range.
+fn parse_range(from: Option<i64>, to: Option<i64>) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    let to = match to {
+        Some(to) => from_millis(to)?,
+        None => Local::now(),
+    };
+    let from = match from {
+        Some(from) => from_millis(from)?,
+        None => to.checked_sub_signed(Duration::minutes(DEFAULT_PERIOD_MINUTES))?,
+    };
+    Some((from, to))
+}
+
+/// Converts the Unix time milliseconds into the timestamp, `None` if it's out of range.
+fn from_millis(millis: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_millis_opt(millis).single()
+}
+
+/// Aggregates the numeric readings within the intervals aligned to the Unix epoch.
+/// Non-numeric readings are skipped.
+fn aggregate_readings(readings: &[Reading], interval: Duration, aggregate: Aggregate) -> Vec<Bucket> {
+    let interval_millis = interval.num_milliseconds();
+    readings
+        .iter()
+        .filter_map(|reading| {
+            f64::try_from(&reading.value)
+                .ok()
+                .or_else(|| i64::try_from(&reading.value).ok().map(|value| value as f64))
+                .map(|value| (reading.timestamp.timestamp_millis(), value))
+        })
+        .group_by(|(timestamp, _)| timestamp.div_euclid(interval_millis) * interval_millis)
+        .into_iter()
+        .map(|(timestamp, group)| {
+            let values = group.map(|(_, value)| value).collect_vec();
+            Bucket {
+                timestamp: Local.timestamp_millis(timestamp),
+                value: match aggregate {
+                    Aggregate::Avg => values.iter().sum::<f64>() / values.len() as f64,
+                    Aggregate::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+                    Aggregate::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                    Aggregate::Sum => values.iter().sum(),
+                    Aggregate::Count => values.len() as f64,
+                },
+                count: values.len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate_readings_ok() {
+        let readings = vec![
+            Reading {
+                timestamp: Local.timestamp_millis(60_000),
+                value: Value::Temperature(20.0),
+            },
+            Reading {
+                timestamp: Local.timestamp_millis(90_000),
+                value: Value::Temperature(22.0),
+            },
+            Reading {
+                timestamp: Local.timestamp_millis(120_000),
+                value: Value::Counter(42),
+            },
+        ];
+        assert_eq!(
+            aggregate_readings(&readings, Duration::minutes(1), Aggregate::Avg),
+            vec![
+                Bucket {
+                    timestamp: Local.timestamp_millis(60_000),
+                    value: 21.0,
+                    count: 2,
+                },
+                Bucket {
+                    timestamp: Local.timestamp_millis(120_000),
+                    value: 42.0,
+                    count: 1,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn aggregate_readings_skips_non_numeric() {
+        let readings = vec![Reading {
+            timestamp: Local.timestamp_millis(60_000),
+            value: Value::Text("hello".into()),
+        }];
+        assert_eq!(
+            aggregate_readings(&readings, Duration::minutes(1), Aggregate::Max),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Web interface authentication.
+//!
+//! Users are defined in the settings. Browsers authenticate with a session cookie,
+//! and API clients authenticate with an `Authorization: Bearer <token>` header.
+//! If no users are defined, the authentication is disabled and everyone is an administrator.
+
+use chrono::Duration;
+use rand::Rng;
+use rocket::http::{Cookie, Cookies, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Request, State};
+use subtle::ConstantTimeEq;
+
+use crate::prelude::*;
+use crate::settings::{Role, Settings};
+use crate::web::context::Context;
+
+pub const SESSION_COOKIE_NAME: &str = "session";
+
+/// Session lifetime, it's prolonged with every sign in.
+const SESSION_DAYS: i64 = 30;
+
+/// Authenticated user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    /// User name or `None` when the authentication is disabled.
+    pub name: Option<String>,
+
+    pub role: Role,
+}
+
+/// Authenticated user with the `Admin` role.
+pub struct Admin(pub User);
+
+impl User {
+    /// User which is used when the authentication is disabled.
+    fn anonymous() -> Self {
+        Self {
+            name: None,
+            role: Role::Admin,
+        }
+    }
+
+    /// Finds the user by the API token.
+    ///
+    /// The tokens are compared in constant time, so that the response time doesn't leak them.
+    fn from_api_token(settings: &Settings, token: &str) -> Option<Self> {
+        settings
+            .http
+            .users
+            .iter()
+            .find(|(_, user)| {
+                user.secrets
+                    .api_tokens
+                    .iter()
+                    .any(|api_token| bool::from(api_token.as_bytes().ct_eq(token.as_bytes())))
+            })
+            .map(|(name, user)| Self {
+                name: Some(name.clone()),
+                role: user.role,
+            })
+    }
+
+    /// Finds the user by the session token.
+    fn from_session(settings: &Settings, db: &Connection, token: &str) -> Result<Option<Self>> {
+        let name = match task::block_on(db.get_user_data::<String>(&session_key(token)))? {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        // The user may have been deleted from the settings since the session started.
+        Ok(settings.http.users.get(&name).map(|user| Self {
+            name: Some(name),
+            role: user.role,
+        }))
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for User {
+    type Error = ();
+
+    /// The outcome is cached, since the request context needs the user too.
+    fn from_request(request: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        match request.local_cache(|| authenticate(request)) {
+            Ok(user) => Outcome::Success(user.clone()),
+            Err(status) => Outcome::Failure((*status, ())),
+        }
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Admin {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        match request.guard::<User>() {
+            Outcome::Success(user) if user.role == Role::Admin => Outcome::Success(Admin(user)),
+            Outcome::Success(_) => Outcome::Failure((Status::Forbidden, ())),
+            Outcome::Failure(failure) => Outcome::Failure(failure),
+            Outcome::Forward(forward) => Outcome::Forward(forward),
+        }
+    }
+}
+
+/// Authenticates the request either by the API token or by the session cookie.
+fn authenticate(request: &Request) -> StdResult<User, Status> {
+    let settings = match request.guard::<State<Settings>>().succeeded() {
+        Some(settings) => settings,
+        None => return Err(Status::InternalServerError),
+    };
+    if settings.http.users.is_empty() {
+        return Ok(User::anonymous());
+    }
+
+    if let Some(token) = request
+        .headers()
+        .get_one("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return match User::from_api_token(&settings, token.trim()) {
+            Some(user) => Ok(user),
+            None => Err(Status::Unauthorized),
+        };
+    }
+
+    let db = match request.guard::<State<Connection>>().succeeded() {
+        Some(db) => db,
+        None => return Err(Status::InternalServerError),
+    };
+    let token = match request.cookies().get(SESSION_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_string(),
+        None => return Err(Status::Unauthorized),
+    };
+    match User::from_session(&settings, &db, &token) {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(Status::Unauthorized),
+        Err(error) => {
+            error!("Failed to check the session: {}", error);
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+/// Verifies the user credentials.
+pub fn verify_password(settings: &Settings, name: &str, password: &str) -> bool {
+    settings.http.users.get(name).map_or(false, |user| {
+        argon2::verify_encoded(&user.secrets.password_hash, password.as_bytes()).unwrap_or_else(|error| {
+            error!("Invalid password hash of `{}`: {}", name, error);
+            false
+        })
+    })
+}
+
+/// Hashes the password, the result is meant to be put into the settings.
+pub fn hash_password(password: &str) -> Result<String> {
+    Ok(argon2::hash_encoded(
+        password.as_bytes(),
+        &rand::thread_rng().gen::<[u8; 16]>(),
+        &argon2::Config::default(),
+    )?)
+}
+
+/// Starts a new session and sets the session cookie.
+pub fn sign_in(context: &Context, db: &Connection, cookies: &mut Cookies, name: &str) -> Result {
+    let token = rand::thread_rng()
+        .gen::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    task::block_on(db.set_user_data(
+        &session_key(&token),
+        name,
+        Some(Local::now() + Duration::days(SESSION_DAYS)),
+    ))?;
+    cookies.add(Cookie::parse(format!(
+        "{}={}; Path={}/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        SESSION_COOKIE_NAME,
+        token,
+        context.base_path,
+        Duration::days(SESSION_DAYS).num_seconds(),
+        if context.is_secure { "; Secure" } else { "" },
+    ))?);
+    Ok(())
+}
+
+/// Ends the current session, if any, and removes the session cookie.
+pub fn sign_out(context: &Context, db: &Connection, cookies: &mut Cookies) -> Result {
+    if let Some(cookie) = cookies.get(SESSION_COOKIE_NAME) {
+        task::block_on(db.delete_user_data(&session_key(cookie.value())))?;
+    }
+    let mut cookie = Cookie::named(SESSION_COOKIE_NAME);
+    cookie.set_path(format!("{}/", context.base_path));
+    cookies.remove(cookie);
+    Ok(())
+}
+
+fn session_key(token: &str) -> String {
+    format!("web::session::{}", token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_password_ok() -> Result {
+        let settings: Settings = toml::from_str(&format!(
+            r#"
+                [http.users.alice.secrets]
+                password_hash = "{}"
+            "#,
+            hash_password("secret")?,
+        ))?;
+        assert!(verify_password(&settings, "alice", "secret"));
+        assert!(!verify_password(&settings, "alice", "wrong"));
+        assert!(!verify_password(&settings, "bob", "secret"));
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use rocket::request::{FormItems, FromForm};
+
+use crate::prelude::*;
+
+/// Action to apply to the selected sensors on the sensor management page.
+#[derive(PartialEq, Debug)]
+pub enum BulkAction {
+    /// Delete the sensors alongside with their readings.
+    Delete,
+
+    /// Hide the sensors from the dashboard.
+    Hide,
+
+    /// Show the hidden sensors on the dashboard again.
+    Unhide,
+
+    /// Delete the readings older than the specified number of days.
+    DeleteReadingsOlderThan(i64),
+}
+
+/// Sensor management form.
+///
+/// Implemented manually because the derived `FromForm` doesn't support repeated fields,
+/// and the form contains a checkbox per sensor.
+#[derive(PartialEq, Debug)]
+pub struct BulkActionForm {
+    pub action: BulkAction,
+    pub sensor_ids: Vec<String>,
+}
+
+impl<'f> FromForm<'f> for BulkActionForm {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> StdResult<Self, Self::Error> {
+        let mut action = None;
+        let mut older_than_days = None;
+        let mut sensor_ids = Vec::new();
+
+        for item in items {
+            let (key, value) = item.key_value_decoded();
+            match key.as_str() {
+                "action" => action = Some(value),
+                "sensor_id" => sensor_ids.push(value),
+                "older_than_days" if !value.is_empty() => {
+                    older_than_days = Some(value.parse::<i64>().map_err(|error| error.to_string())?)
+                }
+                _ => {}
+            }
+        }
+
+        let action = match action.as_deref() {
+            Some("delete") => BulkAction::Delete,
+            Some("hide") => BulkAction::Hide,
+            Some("unhide") => BulkAction::Unhide,
+            Some("delete_readings") => BulkAction::DeleteReadingsOlderThan(
+                older_than_days.ok_or_else(|| "`older_than_days` is required".to_string())?,
+            ),
+            Some(action) => return Err(format!("unknown action: `{}`", action)),
+            None => return Err("`action` is required".into()),
+        };
+
+        Ok(Self { action, sensor_ids })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::request::FormItems;
+
+    use super::*;
+
+    #[test]
+    fn repeated_sensor_ids_ok() {
+        let form = BulkActionForm::from_form(&mut FormItems::from("action=hide&sensor_id=a%3A%3Ab&sensor_id=c"), true);
+        assert_eq!(
+            form,
+            Ok(BulkActionForm {
+                action: BulkAction::Hide,
+                sensor_ids: vec!["a::b".into(), "c".into()],
+            })
+        );
+    }
+
+    #[test]
+    fn delete_readings_requires_days() {
+        let form = BulkActionForm::from_form(&mut FormItems::from("action=delete_readings&sensor_id=a"), true);
+        assert!(form.is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use rocket::request::{FormItems, FromForm};
+
+use crate::prelude::*;
+
+/// Default comparison chart period.
+pub const DEFAULT_MINUTES: i64 = 1440;
+
+/// Sensors to compare on a single chart, parsed from the query string.
+///
+/// Implemented manually because the derived `FromForm` doesn't support repeated fields,
+/// and the selection is encoded as repeated `sensor_id` parameters to keep the URL shareable.
+#[derive(PartialEq, Debug)]
+pub struct ComparisonForm {
+    pub sensor_ids: Vec<String>,
+    pub minutes: i64,
+}
+
+impl<'f> FromForm<'f> for ComparisonForm {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> StdResult<Self, Self::Error> {
+        let mut sensor_ids = Vec::new();
+        let mut minutes = DEFAULT_MINUTES;
+
+        for item in items {
+            let (key, value) = item.key_value_decoded();
+            match key.as_str() {
+                "sensor_id" if !sensor_ids.contains(&value) => sensor_ids.push(value),
+                "minutes" if !value.is_empty() => minutes = value.parse::<i64>().map_err(|error| error.to_string())?,
+                _ => {}
+            }
+        }
+
+        Ok(Self { sensor_ids, minutes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::request::FormItems;
+
+    use super::*;
+
+    #[test]
+    fn repeated_sensor_ids_ok() {
+        let form = ComparisonForm::from_form(
+            &mut FormItems::from("sensor_id=a%3A%3Ab&sensor_id=c&sensor_id=c&minutes=60"),
+            true,
+        );
+        assert_eq!(
+            form,
+            Ok(ComparisonForm {
+                sensor_ids: vec!["a::b".into(), "c".into()],
+                minutes: 60,
+            })
+        );
+    }
+
+    #[test]
+    fn empty_ok() {
+        let form = ComparisonForm::from_form(&mut FormItems::from(""), true);
+        assert_eq!(
+            form,
+            Ok(ComparisonForm {
+                sensor_ids: vec![],
+                minutes: DEFAULT_MINUTES,
+            })
+        );
+    }
+}
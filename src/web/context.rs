@@ -0,0 +1,97 @@
+//! Request context which depends on how the web server is published.
+
+use std::fmt::Display;
+use std::net::IpAddr;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Request, State};
+
+use crate::settings::{HttpSettings, Locale, Role, Settings};
+use crate::web::auth::User;
+use crate::web::i18n;
+
+/// Request context, it takes the reverse proxy `X-Forwarded-*` headers into account if they're trusted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    /// Path prefix the web interface is published under, without the trailing slash.
+    pub base_path: String,
+
+    /// Whether the browser has connected via HTTPS.
+    pub is_secure: bool,
+
+    /// The original client address.
+    pub client_ip: Option<IpAddr>,
+
+    /// The web interface language.
+    pub locale: Locale,
+
+    /// Whether the user is allowed to change anything, for example to control the sensors.
+    pub is_admin: bool,
+}
+
+impl Context {
+    fn new(settings: &HttpSettings, request: &Request) -> Self {
+        let headers = request.headers();
+        let forwarded = |name: &str| {
+            if settings.trust_forwarded_headers {
+                headers.get_one(name).map(str::trim).filter(|value| !value.is_empty())
+            } else {
+                None
+            }
+        };
+        Self {
+            base_path: normalize_base_path(forwarded("X-Forwarded-Prefix").unwrap_or(&settings.base_path)),
+            is_secure: forwarded("X-Forwarded-Proto").map_or(settings.tls.is_some(), |proto| proto == "https"),
+            client_ip: forwarded("X-Forwarded-For")
+                .and_then(|value| value.split(',').next())
+                .and_then(|ip| ip.trim().parse().ok())
+                .or_else(|| request.client_ip()),
+            locale: i18n::negotiate(settings, request),
+            is_admin: request
+                .guard::<User>()
+                .succeeded()
+                .map_or(false, |user| user.role == Role::Admin),
+        }
+    }
+
+    /// Prepends the base path to the server-relative URI, for example to redirect the browser.
+    pub fn uri(&self, uri: impl Display) -> String {
+        format!("{}{}", self.base_path, uri)
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Context {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<Self, Self::Error> {
+        match request.guard::<State<Settings>>().succeeded() {
+            Some(settings) => Outcome::Success(Self::new(&settings.http, request)),
+            None => Outcome::Failure((Status::InternalServerError, ())),
+        }
+    }
+}
+
+/// Makes the base path either empty or starting with a slash and without the trailing slash.
+fn normalize_base_path(base_path: &str) -> String {
+    let base_path = base_path.trim_matches('/');
+    if base_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", base_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_base_path_ok() {
+        assert_eq!(normalize_base_path(""), "");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("iot"), "/iot");
+        assert_eq!(normalize_base_path("/iot/"), "/iot");
+        assert_eq!(normalize_base_path("/my/iot"), "/my/iot");
+    }
+}
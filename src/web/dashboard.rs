@@ -0,0 +1,152 @@
+//! User-defined dashboards.
+
+use chrono::Duration;
+
+use crate::prelude::*;
+use crate::settings::{Dashboard, Tile, Widget};
+
+/// Sparkline SVG view box width.
+const SPARKLINE_WIDTH: f64 = 100.0;
+
+/// Sparkline SVG view box height.
+const SPARKLINE_HEIGHT: f64 = 30.0;
+
+/// Dashboard section with the loaded tiles.
+pub struct SectionView {
+    pub title: Option<String>,
+    pub tiles: Vec<TileView>,
+}
+
+/// Dashboard tile with the loaded sensor.
+pub struct TileView {
+    pub tile: Tile,
+
+    /// The sensor and its latest reading, `None` if the sensor doesn't exist.
+    pub actual: Option<(Sensor, Reading)>,
+
+    /// SVG polyline points of the recent readings, if the widget is `Sparkline`.
+    pub sparkline: Option<String>,
+}
+
+/// Loads the sensors and readings needed to render the dashboard.
+pub async fn load(db: &Connection, dashboard: &Dashboard) -> Result<Vec<SectionView>> {
+    let mut sections = Vec::with_capacity(dashboard.sections.len());
+    for section in dashboard.sections.iter() {
+        let mut tiles = Vec::with_capacity(section.tiles.len());
+        for tile in section.tiles.iter() {
+            let actual = db.select_sensor(&tile.sensor_id).await?;
+            let sparkline = if tile.widget == Widget::Sparkline && actual.is_some() {
+                let since = Local::now() - Duration::minutes(tile.minutes);
+                Some(sparkline_points(&db.select_readings(&tile.sensor_id, &since).await?))
+            } else {
+                None
+            };
+            tiles.push(TileView {
+                tile: tile.clone(),
+                actual,
+                sparkline,
+            });
+        }
+        sections.push(SectionView {
+            title: section.title.clone(),
+            tiles,
+        });
+    }
+    Ok(sections)
+}
+
+/// Scales the numeric readings to fit the sparkline view box. Non-numeric readings are skipped.
+fn sparkline_points(readings: &[Reading]) -> String {
+    let points: Vec<(i64, f64)> = readings
+        .iter()
+        .filter_map(|reading| {
+            f64::try_from(&reading.value)
+                .ok()
+                .map(|value| (reading.timestamp.timestamp_millis(), value))
+        })
+        .collect();
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return String::new(),
+    };
+    let min = points.iter().map(|(_, value)| *value).fold(f64::INFINITY, f64::min);
+    let max = points.iter().map(|(_, value)| *value).fold(f64::NEG_INFINITY, f64::max);
+    let scale_x = |timestamp: i64| {
+        if last > first {
+            (timestamp - first) as f64 / (last - first) as f64 * SPARKLINE_WIDTH
+        } else {
+            SPARKLINE_WIDTH
+        }
+    };
+    let scale_y = |value: f64| {
+        if max > min {
+            SPARKLINE_HEIGHT - (value - min) / (max - min) * SPARKLINE_HEIGHT
+        } else {
+            SPARKLINE_HEIGHT / 2.0
+        }
+    };
+    points
+        .iter()
+        .map(|(timestamp, value)| format!("{:.2},{:.2}", scale_x(*timestamp), scale_y(*value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl TileView {
+    /// Returns the tile title, falling back to the sensor title and then to the sensor ID.
+    pub fn title(&self) -> String {
+        match (&self.tile.title, &self.actual) {
+            (Some(title), _) => title.clone(),
+            (None, Some((sensor, _))) => sensor.title(),
+            (None, None) => self.tile.sensor_id.clone(),
+        }
+    }
+
+    /// Returns the `Gauge` fill percentage.
+    pub fn gauge_percentage(&self) -> f64 {
+        let value = match self
+            .actual
+            .as_ref()
+            .and_then(|(_, reading)| f64::try_from(&reading.value).ok())
+        {
+            Some(value) => value,
+            None => return 0.0,
+        };
+        if self.tile.max > self.tile.min {
+            ((value - self.tile.min) / (self.tile.max - self.tile.min) * 100.0)
+                .max(0.0)
+                .min(100.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparkline_points_ok() {
+        let readings = vec![
+            Reading {
+                timestamp: Local.timestamp_millis(0),
+                value: Value::Temperature(10.0),
+            },
+            Reading {
+                timestamp: Local.timestamp_millis(1000),
+                value: Value::Text("skipped".into()),
+            },
+            Reading {
+                timestamp: Local.timestamp_millis(2000),
+                value: Value::Temperature(20.0),
+            },
+        ];
+        assert_eq!(sparkline_points(&readings), "0.00,30.00 100.00,0.00");
+    }
+
+    #[test]
+    fn sparkline_points_empty() {
+        assert_eq!(sparkline_points(&[]), "");
+    }
+}
@@ -0,0 +1,242 @@
+//! Sensor reading history table.
+
+use std::fmt::{self, Write};
+
+use rocket::http::uri::{Formatter, Query, UriDisplay};
+use rocket::request::{FormItems, FromForm};
+
+use crate::core::db::reading_query::ReadingQuery;
+use crate::prelude::*;
+use crate::web::range::Range;
+
+/// Number of the readings per page.
+pub const PAGE_SIZE: usize = 50;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SortKey {
+    Timestamp,
+
+    /// Numeric values are compared as numbers, the others – as their plain text.
+    Value,
+}
+
+/// History table query: the range, sorting, optional value bounds and the page number.
+///
+/// Parsed from the [`Range`] query items along with `sort` (`timestamp` or `value`),
+/// `order` (`asc` or `desc`), `min`, `max` and `page` (starting with `1`).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HistoryQuery {
+    pub range: Range,
+    pub sort: SortKey,
+    pub descending: bool,
+
+    /// Inclusive lower bound of the numeric value.
+    pub min: Option<f64>,
+
+    /// Inclusive upper bound of the numeric value.
+    pub max: Option<f64>,
+
+    pub page: usize,
+}
+
+impl HistoryQuery {
+    pub fn new(range: Range) -> Self {
+        Self {
+            range,
+            sort: SortKey::Timestamp,
+            descending: true,
+            min: None,
+            max: None,
+            page: 1,
+        }
+    }
+
+    /// Returns the database query, which filters and sorts the readings.
+    pub fn reading_query(&self) -> ReadingQuery {
+        ReadingQuery {
+            since: self.range.since,
+            until: self.range.until,
+            min: self.min,
+            max: self.max,
+            by_value: self.sort == SortKey::Value,
+            descending: self.descending,
+        }
+    }
+
+    /// Returns the number of the readings before the current page.
+    pub fn offset(&self) -> i64 {
+        i64::try_from((self.page - 1).saturating_mul(PAGE_SIZE)).unwrap_or(i64::MAX)
+    }
+
+    pub fn with_range(self, range: Range) -> Self {
+        Self { range, page: 1, ..self }
+    }
+
+    pub fn with_page(self, page: usize) -> Self {
+        Self { page, ..self }
+    }
+
+    /// Sorts by the key, or reverses the order if it's already sorted by the key.
+    pub fn with_sort(self, sort: SortKey) -> Self {
+        Self {
+            sort,
+            descending: if self.sort == sort { !self.descending } else { true },
+            page: 1,
+            ..self
+        }
+    }
+}
+
+impl<'f> FromForm<'f> for HistoryQuery {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> StdResult<Self, Self::Error> {
+        let items: Vec<(String, String)> = items.map(|item| item.key_value_decoded()).collect();
+        let mut query = Self::new(Range::from_items(items.iter().cloned())?);
+
+        for (key, value) in items {
+            match key.as_str() {
+                "sort" => {
+                    query.sort = match value.as_str() {
+                        "timestamp" => SortKey::Timestamp,
+                        "value" => SortKey::Value,
+                        _ => return Err(format!("unknown sort key: `{}`", value)),
+                    }
+                }
+                "order" => query.descending = value != "asc",
+                "min" if !value.is_empty() => query.min = Some(value.parse().map_err(|_| "invalid `min`")?),
+                "max" if !value.is_empty() => query.max = Some(value.parse().map_err(|_| "invalid `max`")?),
+                "page" if !value.is_empty() => {
+                    query.page = match value.parse() {
+                        Ok(page) if page >= 1 => page,
+                        _ => return Err(format!("invalid page: `{}`", value)),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+impl UriDisplay<Query> for HistoryQuery {
+    fn fmt(&self, f: &mut Formatter<Query>) -> fmt::Result {
+        UriDisplay::fmt(&self.range, f)?;
+        if self.sort == SortKey::Value {
+            f.write_named_value("sort", "value")?;
+        }
+        if !self.descending {
+            f.write_named_value("order", "asc")?;
+        }
+        if let Some(min) = self.min {
+            f.write_named_value("min", min)?;
+        }
+        if let Some(max) = self.max {
+            f.write_named_value("max", max)?;
+        }
+        if self.page != 1 {
+            f.write_named_value("page", self.page)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the page count for the number of the readings, at least one.
+pub fn page_count(reading_count: usize) -> usize {
+    ((reading_count + PAGE_SIZE - 1) / PAGE_SIZE).max(1)
+}
+
+/// Formats the readings as CSV with the `timestamp`, `type` and `value` columns.
+pub fn to_csv(readings: &[Reading]) -> String {
+    let mut csv = String::from("timestamp,type,value\r\n");
+    for reading in readings {
+        let _ = write!(
+            csv,
+            "{},{},{}\r\n",
+            reading.timestamp.to_rfc3339(),
+            reading.value.type_name(),
+            escape_csv(&plain_value(&reading.value)),
+        );
+    }
+    csv
+}
+
+/// Formats the value without the units and markup.
+pub fn plain_value(value: &Value) -> String {
+    match value {
+        Value::None | Value::Other => String::new(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Text(text) | Value::StringEnum(text) | Value::ImageUrl(text) => text.clone(),
+        Value::Blob(content) => format!("{} bytes", content.len()),
+        value => value.to_f64().map(|value| value.to_string()).unwrap_or_default(),
+    }
+}
+
+/// Quotes the field if it contains a separator, a quote or a line break.
+fn escape_csv(field: &str) -> String {
+    if field.contains(&[',', '"', '\r', '\n'][..]) {
+        format!(r#""{}""#, field.replace('"', r#""""#))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ok() {
+        let query = HistoryQuery::from_form(
+            &mut FormItems::from("from=1000&to=3000&sort=value&order=asc&min=&max=22&page=2"),
+            true,
+        )
+        .unwrap();
+        assert_eq!(query.range.since, Local.timestamp_millis(1000));
+        assert_eq!(query.sort, SortKey::Value);
+        assert!(!query.descending);
+        assert_eq!(query.min, None);
+        assert_eq!(query.max, Some(22.0));
+        assert_eq!(query.page, 2);
+    }
+
+    #[test]
+    fn invalid_page_err() {
+        assert!(HistoryQuery::from_form(&mut FormItems::from("page=0"), true).is_err());
+    }
+
+    #[test]
+    fn with_sort_ok() {
+        let query = HistoryQuery::new(Range::last_minutes(60)).with_sort(SortKey::Value);
+        assert!(query.descending);
+        assert!(query.reading_query().by_value);
+        assert!(!query.with_sort(SortKey::Value).descending);
+    }
+
+    #[test]
+    fn offset_ok() {
+        let query = HistoryQuery::new(Range::last_minutes(60));
+        assert_eq!(query.with_page(1).offset(), 0);
+        assert_eq!(query.with_page(3).offset(), 2 * PAGE_SIZE as i64);
+        assert_eq!(query.with_page(usize::MAX).offset(), i64::MAX);
+    }
+
+    #[test]
+    fn page_count_ok() {
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(PAGE_SIZE), 1);
+        assert_eq!(page_count(PAGE_SIZE + 1), 2);
+    }
+
+    #[test]
+    fn to_csv_ok() {
+        let reading = Message::new("test")
+            .value(Value::Text("Hello, \"world\"".into()))
+            .timestamp(Local.timestamp_millis(0))
+            .reading;
+        let csv = to_csv(&[reading]);
+        assert!(csv.starts_with("timestamp,type,value\r\n"));
+        assert!(csv.ends_with(",Text,\"Hello, \"\"world\"\"\"\r\n"));
+    }
+}
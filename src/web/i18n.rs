@@ -0,0 +1,258 @@
+//! Web interface translations and locale-dependent formatting.
+//!
+//! Catalogues map the English text to the translation. An entry may contain a single `{}` placeholder
+//! to translate the generated sensor titles, for example: `"{} Online" = "{} online"`.
+//!
+//! Plural forms are the `"text#category"` entries, where the category is one of the
+//! [CLDR plural categories](https://cldr.unicode.org/index/cldr-spec/plural-rules), for example:
+//! `"readings#few" = "показания"`. The plain text entry is the fallback.
+//!
+//! The locale is negotiated per request and kept in a thread-local, since Rocket handles
+//! a request, including the template rendering, on a single worker thread.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+
+use lazy_static::lazy_static;
+use rocket::Request;
+
+use crate::prelude::*;
+use crate::settings::{HttpSettings, Locale};
+
+type Catalogue = HashMap<String, String>;
+
+lazy_static! {
+    static ref NL: Catalogue = toml::from_str(include_str!("locales/nl.toml")).unwrap();
+    static ref RU: Catalogue = toml::from_str(include_str!("locales/ru.toml")).unwrap();
+}
+
+thread_local! {
+    static CURRENT_LOCALE: Cell<Locale> = Cell::new(Locale::En);
+}
+
+/// Returns the locale of the request currently handled by the thread.
+pub fn current() -> Locale {
+    CURRENT_LOCALE.with(Cell::get)
+}
+
+pub fn set_current(locale: Locale) {
+    CURRENT_LOCALE.with(|current| current.set(locale));
+}
+
+/// Picks the configured locale, or the one preferred by the browser.
+pub fn negotiate(settings: &HttpSettings, request: &Request) -> Locale {
+    settings
+        .locale
+        .or_else(|| {
+            request
+                .headers()
+                .get_one("Accept-Language")
+                .and_then(from_accept_language)
+        })
+        .unwrap_or_default()
+}
+
+/// Picks the supported language with the highest quality, for example: `nl-NL,nl;q=0.9,en;q=0.8`.
+pub fn from_accept_language(header: &str) -> Option<Locale> {
+    header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let locale = parse(parts.next()?.trim())?;
+            let quality = parts
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f64>().ok())
+                .unwrap_or(1.0);
+            Some((locale, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .fold(None, |best: Option<(Locale, f64)>, (locale, quality)| match best {
+            Some((_, best_quality)) if best_quality >= quality => best,
+            _ => Some((locale, quality)),
+        })
+        .map(|(locale, _)| locale)
+}
+
+/// Parses the language tag, ignoring the region.
+fn parse(tag: &str) -> Option<Locale> {
+    match tag.split('-').next()?.to_lowercase().as_str() {
+        "en" => Some(Locale::En),
+        "nl" => Some(Locale::Nl),
+        "ru" => Some(Locale::Ru),
+        _ => None,
+    }
+}
+
+impl Locale {
+    /// Returns the [language tag](https://www.w3.org/International/articles/language-tags/).
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Nl => "nl",
+            Locale::Ru => "ru",
+        }
+    }
+
+    fn catalogue(self) -> Option<&'static Catalogue> {
+        match self {
+            Locale::En => None,
+            Locale::Nl => Some(&NL),
+            Locale::Ru => Some(&RU),
+        }
+    }
+
+    /// Translates the text, falls back to the text itself if there's no translation.
+    pub fn translate(self, text: &str) -> Cow<str> {
+        let catalogue = match self.catalogue() {
+            Some(catalogue) => catalogue,
+            None => return Cow::Borrowed(text),
+        };
+        if let Some(translation) = catalogue.get(text) {
+            return Cow::Owned(translation.clone());
+        }
+        // The most specific pattern wins.
+        catalogue
+            .iter()
+            .filter_map(|(pattern, translation)| {
+                let (prefix, suffix) = split_placeholder(pattern)?;
+                if text.len() > prefix.len() + suffix.len() && text.starts_with(prefix) && text.ends_with(suffix) {
+                    let argument = &text[prefix.len()..text.len() - suffix.len()];
+                    Some((prefix.len() + suffix.len(), translation.replacen("{}", argument, 1)))
+                } else {
+                    None
+                }
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(Cow::Borrowed(text), |(_, translation)| Cow::Owned(translation))
+    }
+
+    /// Translates the text, which follows the count, in the plural form for the count.
+    pub fn translate_plural(self, text: &str, count: u64) -> Cow<str> {
+        self.translate_plural_category(text, self.plural_category(count))
+    }
+
+    /// Returns the plural forms of the text by the plural category, so that the browser could pick one.
+    pub fn plural_forms(self, text: &str) -> HashMap<&'static str, String> {
+        let categories: &[&'static str] = match self {
+            Locale::En | Locale::Nl => &["one", "other"],
+            Locale::Ru => &["one", "few", "many", "other"],
+        };
+        categories
+            .iter()
+            .map(|category| (*category, self.translate_plural_category(text, category).into_owned()))
+            .collect()
+    }
+
+    /// Returns the plural category of the non-negative integer.
+    fn plural_category(self, count: u64) -> &'static str {
+        match self {
+            Locale::En | Locale::Nl if count == 1 => "one",
+            Locale::En | Locale::Nl => "other",
+            Locale::Ru => match (count % 10, count % 100) {
+                (1, remainder) if remainder != 11 => "one",
+                (2..=4, remainder) if !(12..=14).contains(&remainder) => "few",
+                _ => "many",
+            },
+        }
+    }
+
+    fn translate_plural_category<'a>(self, text: &'a str, category: &str) -> Cow<'a, str> {
+        match self
+            .catalogue()
+            .and_then(|catalogue| catalogue.get(&format!("{}#{}", text, category)))
+        {
+            Some(translation) => Cow::Owned(translation.clone()),
+            None => self.translate(text),
+        }
+    }
+
+    pub fn datetime_format(self) -> &'static str {
+        match self {
+            Locale::En => "%b %d, %H:%M:%S",
+            Locale::Nl => "%d-%m, %H:%M:%S",
+            Locale::Ru => "%d.%m, %H:%M:%S",
+        }
+    }
+
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Locale::En => '.',
+            Locale::Nl | Locale::Ru => ',',
+        }
+    }
+
+    /// Replaces the decimal point in the formatted number.
+    pub fn localize_number(self, number: &str) -> String {
+        number.replace('.', &self.decimal_separator().to_string())
+    }
+}
+
+fn split_placeholder(pattern: &str) -> Option<(&str, &str)> {
+    let index = pattern.find("{}")?;
+    Some((&pattern[..index], &pattern[index + 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogues_ok() {
+        assert!(!NL.is_empty());
+        assert!(!RU.is_empty());
+    }
+
+    #[test]
+    fn every_entry_is_translated_ok() {
+        for (catalogue, other) in &[(&*NL, &*RU), (&*RU, &*NL)] {
+            // Plural forms differ between the languages.
+            for text in catalogue.keys().filter(|text| !text.contains('#')) {
+                assert!(other.contains_key(text), "missing translation: `{}`", text);
+            }
+        }
+    }
+
+    #[test]
+    fn translate_ok() {
+        assert_eq!(Locale::Nl.translate("Sensors"), "Sensoren");
+        assert_eq!(Locale::En.translate("Sensors"), "Sensors");
+        assert_eq!(Locale::Ru.translate("Untranslated"), "Untranslated");
+    }
+
+    #[test]
+    fn translate_placeholder_ok() {
+        assert_eq!(Locale::Nl.translate("Living Room Online"), "Living Room online");
+    }
+
+    #[test]
+    fn translate_plural_ok() {
+        assert_eq!(Locale::Ru.translate_plural("readings", 1), "показание");
+        assert_eq!(Locale::Ru.translate_plural("readings", 22), "показания");
+        assert_eq!(Locale::Ru.translate_plural("readings", 12), "показаний");
+        assert_eq!(Locale::Ru.translate_plural("readings", 111), "показаний");
+        assert_eq!(Locale::Nl.translate_plural("readings", 1), "meting");
+        assert_eq!(Locale::Nl.translate_plural("readings", 2), "metingen");
+        assert_eq!(Locale::En.translate_plural("readings", 1), "readings");
+    }
+
+    #[test]
+    fn plural_forms_ok() {
+        let forms = Locale::Ru.plural_forms("days");
+        assert_eq!(forms["few"], "дня");
+        assert_eq!(forms["other"], "дней");
+    }
+
+    #[test]
+    fn accept_language_ok() {
+        assert_eq!(from_accept_language("nl-NL,nl;q=0.9,en;q=0.8"), Some(Locale::Nl));
+        assert_eq!(from_accept_language("de-DE,en;q=0.5,ru;q=0.7"), Some(Locale::Ru));
+        assert_eq!(from_accept_language("de-DE"), None);
+        assert_eq!(from_accept_language("ru;q=0"), None);
+    }
+
+    #[test]
+    fn localize_number_ok() {
+        assert_eq!(Locale::Ru.localize_number("21.5 ℃"), "21,5 ℃");
+        assert_eq!(Locale::En.localize_number("21.5 ℃"), "21.5 ℃");
+    }
+}
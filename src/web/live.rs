@@ -0,0 +1,170 @@
+//! Live updates via [Server-Sent Events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events).
+
+use std::io::{self, Read};
+
+use regex::Regex;
+use serde_json::json;
+
+use crate::prelude::*;
+use crate::web::templates::filters;
+
+/// Send a comment this often to keep the connection alive and to detect disconnected clients.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Subscribes to the message bus and forwards the messages to the connected browsers.
+#[derive(Clone)]
+pub struct Broadcaster {
+    subscribers: Arc<Mutex<Vec<Sender>>>,
+}
+
+impl Broadcaster {
+    /// Spawns the task which forwards the bus messages to the subscribers.
+    pub fn spawn(mut rx: Receiver) -> Self {
+        let this = Self {
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+        {
+            let this = this.clone();
+            task::spawn(async move {
+                while let Some(message) = rx.next().await {
+                    // Dropped subscribers are removed on the next message.
+                    this.subscribers
+                        .lock()
+                        .await
+                        .retain(|tx| tx.unbounded_send(message.clone()).is_ok());
+                }
+                warn!("Live updates are stopped.");
+            });
+        }
+        this
+    }
+
+    /// Adds a new subscriber.
+    pub async fn subscribe(&self) -> Receiver {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        self.subscribers.lock().await.push(tx);
+        rx
+    }
+}
+
+/// Streams the messages matching the pattern in the `text/event-stream` format.
+///
+/// Rocket's [`Stream`](rocket::response::Stream) reads the body in chunks. `io::ErrorKind::WouldBlock`
+/// makes Rocket flush the response, so it's returned after each event (requires the `sse` feature).
+pub struct EventStream {
+    rx: Receiver,
+    pattern: Option<Regex>,
+
+    /// The current event which is not yet read out.
+    pending: Vec<u8>,
+
+    /// Tells whether the response should be flushed before waiting for the next event.
+    needs_flush: bool,
+}
+
+impl EventStream {
+    pub fn new(rx: Receiver, pattern: Option<Regex>) -> Self {
+        Self {
+            rx,
+            pattern,
+            pending: Vec::new(),
+            needs_flush: false,
+        }
+    }
+
+    /// Waits for the next event to be sent.
+    fn next_event(&mut self) -> Option<Vec<u8>> {
+        loop {
+            match task::block_on(async_std::future::timeout(KEEP_ALIVE_INTERVAL, self.rx.next())) {
+                Ok(Some(message)) => {
+                    if self
+                        .pattern
+                        .as_ref()
+                        .map_or(true, |pattern| pattern.is_match(&message.sensor.id))
+                    {
+                        return Some(format!("data: {}\n\n", to_event(&message)).into_bytes());
+                    }
+                }
+                Ok(None) => return None,
+                Err(_) => return Some(b": keep-alive\n\n".to_vec()),
+            }
+        }
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            if self.needs_flush {
+                self.needs_flush = false;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            match self.next_event() {
+                Some(event) => {
+                    self.pending = event;
+                    self.needs_flush = true;
+                }
+                None => return Ok(0),
+            }
+        }
+        let size = buf.len().min(self.pending.len());
+        buf[..size].copy_from_slice(&self.pending[..size]);
+        self.pending.drain(..size);
+        Ok(size)
+    }
+}
+
+/// Builds the event payload which is used to update the page in place.
+fn to_event(message: &Message) -> serde_json::Value {
+    let value = &message.reading.value;
+    json!({
+        "type": format!("{:?}", message.type_),
+        "sensor_id": message.sensor.id,
+        "timestamp": message.reading.timestamp.timestamp_millis(),
+        "timestamp_text": filters::format_datetime(&message.reading.timestamp).unwrap_or_default(),
+        "color_class": filters::color_class(value).unwrap_or_default(),
+        // Non-inline values like blobs are too heavy to be sent over the stream.
+        "value_html": if value.is_inline() { Some(value.to_string()) } else { None },
+        "value_debug": if value.is_inline() { Some(format!("{:?}", value)) } else { None },
+        "chart_value": f64::try_from(value).ok().map(|number| number * value.chart_multiplier()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_stream_filters_by_pattern() -> Result {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        tx.unbounded_send(Message::new("foo")).unwrap();
+        tx.unbounded_send(Message::new("bar")).unwrap();
+        drop(tx);
+
+        let mut stream = EventStream::new(rx, Some(Regex::new("^bar$")?));
+        let mut body = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(size) => body.extend_from_slice(&buffer[..size]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        let body = String::from_utf8(body)?;
+
+        assert!(body.starts_with("data: "));
+        assert!(body.contains(r#""sensor_id":"bar""#));
+        assert!(!body.contains(r#""sensor_id":"foo""#));
+        Ok(())
+    }
+
+    #[test]
+    fn blob_event_skips_value_ok() {
+        let message = Message::new("camera").value(Value::Blob(Arc::new(Bytes::from_static(&[0xFF; 1024]))));
+        let event = to_event(&message);
+        assert!(event["value_html"].is_null());
+        assert!(event["value_debug"].is_null());
+    }
+}
@@ -0,0 +1,59 @@
+//! Media detection for the binary sensor values.
+
+/// Binary content kind, which defines how it's displayed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MediaKind {
+    Image,
+    Video,
+
+    /// Offered for download.
+    Other,
+}
+
+/// Detects the MIME type by the content [signature](https://en.wikipedia.org/wiki/List_of_file_signatures).
+pub fn sniff_mime_type(content: &[u8]) -> &'static str {
+    match content {
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [_, _, _, _, b'f', b't', b'y', b'p', b'q', b't', ..] => "video/quicktime",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "video/mp4",
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => "video/webm",
+        [b'%', b'P', b'D', b'F', ..] => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn media_kind(mime_type: &str) -> MediaKind {
+    if mime_type.starts_with("image/") {
+        MediaKind::Image
+    } else if mime_type.starts_with("video/") {
+        MediaKind::Video
+    } else {
+        MediaKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_jpeg_ok() {
+        assert_eq!(sniff_mime_type(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), "image/jpeg");
+    }
+
+    #[test]
+    fn sniff_mp4_ok() {
+        let mime_type = sniff_mime_type(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00");
+        assert_eq!(mime_type, "video/mp4");
+        assert_eq!(media_kind(mime_type), MediaKind::Video);
+    }
+
+    #[test]
+    fn sniff_unknown_ok() {
+        assert_eq!(sniff_mime_type(b"hello"), "application/octet-stream");
+        assert_eq!(sniff_mime_type(b""), "application/octet-stream");
+    }
+}
@@ -0,0 +1,173 @@
+//! [Prometheus exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+
+use std::fmt::Write;
+
+use crate::core::metrics::Snapshot;
+use crate::prelude::*;
+use crate::settings::MetricsSettings;
+
+/// Renders the latest numeric sensor values as gauges along with the process metrics.
+pub fn render(settings: &MetricsSettings, actuals: &[(Sensor, Reading)], snapshot: &Snapshot, db_size: i64) -> String {
+    let mut values = String::new();
+    let mut timestamps = String::new();
+    for (sensor, reading) in actuals {
+        if !settings.is_exported(&sensor.id) {
+            continue;
+        }
+        let value = match reading.value.to_f64() {
+            Some(value) => value,
+            None => continue,
+        };
+        let labels = format!(
+            r#"sensor_id="{}",location="{}",value_type="{}""#,
+            escape(&sensor.id),
+            escape(&sensor.location),
+            reading.value.type_name(),
+        );
+        let _ = writeln!(values, "my_iot_sensor_value{{{}}} {}", labels, value);
+        let _ = writeln!(
+            timestamps,
+            "my_iot_sensor_timestamp_seconds{{{}}} {}",
+            labels,
+            reading.timestamp.timestamp_millis() as f64 / 1000.0,
+        );
+    }
+
+    let mut output = String::new();
+    write_header(
+        &mut output,
+        "my_iot_sensor_value",
+        "gauge",
+        "Latest value of the numeric sensor.",
+    );
+    output.push_str(&values);
+    write_header(
+        &mut output,
+        "my_iot_sensor_timestamp_seconds",
+        "gauge",
+        "Unix time of the latest sensor reading.",
+    );
+    output.push_str(&timestamps);
+    write_metric(
+        &mut output,
+        "my_iot_bus_messages_total",
+        "counter",
+        "Number of the messages dispatched by the bus.",
+        snapshot.bus_messages,
+    );
+    write_metric(
+        &mut output,
+        "my_iot_db_committed_messages_total",
+        "counter",
+        "Number of the messages committed to the database.",
+        snapshot.committed_messages,
+    );
+    write_header(
+        &mut output,
+        "my_iot_db_commit_duration_seconds",
+        "summary",
+        "Database commit latency.",
+    );
+    let _ = writeln!(
+        output,
+        "my_iot_db_commit_duration_seconds_sum {}",
+        snapshot.commit_duration.as_secs_f64(),
+    );
+    let _ = writeln!(output, "my_iot_db_commit_duration_seconds_count {}", snapshot.commits);
+    write_metric(
+        &mut output,
+        "my_iot_db_last_commit_duration_seconds",
+        "gauge",
+        "Duration of the latest database commit.",
+        snapshot.last_commit_duration.as_secs_f64(),
+    );
+    write_metric(
+        &mut output,
+        "my_iot_db_commit_interval_seconds",
+        "gauge",
+        "Current interval between the database commits.",
+        snapshot.commit_interval.as_secs_f64(),
+    );
+    write_metric(
+        &mut output,
+        "my_iot_db_size_bytes",
+        "gauge",
+        "Database size estimated from the page count.",
+        db_size,
+    );
+    output
+}
+
+fn write_header(output: &mut String, name: &str, type_: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, type_);
+}
+
+fn write_metric(output: &mut String, name: &str, type_: &str, help: &str, value: impl std::fmt::Display) {
+    write_header(output, name, type_, help);
+    let _ = writeln!(output, "{} {}", name, value);
+}
+
+/// Escapes the label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', r#"\""#).replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            bus_messages: 42,
+            commits: 2,
+            committed_messages: 40,
+            commit_duration: Duration::from_millis(30),
+            last_commit_duration: Duration::from_millis(10),
+            commit_interval: Duration::from_millis(500),
+        }
+    }
+
+    #[test]
+    fn render_ok() {
+        let actuals = vec![
+            Message::new("test::temperature")
+                .location("Living \"Room\"")
+                .value(Value::Temperature(21.5)),
+            Message::new("test::counter").value(Value::Counter(7)),
+            Message::new("test::text").value(Value::Text("skipped".into())),
+            Message::new("excluded::power").value(Value::Power(100.0)),
+        ]
+        .into_iter()
+        .map(|message| (message.sensor, message.reading))
+        .collect::<Vec<_>>();
+        let settings = MetricsSettings {
+            include: vec![],
+            exclude: vec![Regex::new("^excluded::").unwrap()],
+        };
+
+        let output = render(&settings, &actuals, &snapshot(), 4096);
+        assert!(output.contains(
+            r#"my_iot_sensor_value{sensor_id="test::temperature",location="Living \"Room\"",value_type="Temperature"} 21.5"#
+        ));
+        assert!(output.contains(r#"value_type="Counter"} 7"#));
+        assert!(!output.contains("test::text"));
+        assert!(!output.contains("excluded::power"));
+        assert!(output.contains("my_iot_bus_messages_total 42\n"));
+        assert!(output.contains("my_iot_db_commit_duration_seconds_count 2\n"));
+        assert!(output.contains("my_iot_db_size_bytes 4096\n"));
+    }
+
+    #[test]
+    fn include_ok() {
+        let settings = MetricsSettings {
+            include: vec![Regex::new("^tado::").unwrap()],
+            exclude: vec![Regex::new("::battery$").unwrap()],
+        };
+        assert!(settings.is_exported("tado::home::temperature"));
+        assert!(!settings.is_exported("tado::home::battery"));
+        assert!(!settings.is_exported("youless::power"));
+    }
+}
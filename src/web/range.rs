@@ -0,0 +1,283 @@
+//! Time range of the sensor page.
+
+use std::fmt;
+
+use chrono::Duration;
+use rocket::http::uri::{Formatter, Query, UriDisplay};
+use rocket::request::{FormItems, FromForm};
+
+use crate::core::timezone;
+use crate::prelude::*;
+
+/// Period used when neither a range nor a calendar period is specified.
+const DEFAULT_MINUTES: i64 = 60;
+
+/// Format of the `datetime-local` input.
+pub const DATETIME_LOCAL_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Calendar period.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Half-open `[since, until)` time range.
+///
+/// Parsed from either `from` and `to` (Unix time in milliseconds or `datetime-local` input values),
+/// `period` optionally anchored by `from`, or `minutes` before now.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Range {
+    pub since: DateTime<Local>,
+    pub until: DateTime<Local>,
+
+    /// The calendar period, if the range is one.
+    pub period: Option<Period>,
+
+    /// The number of minutes before now, if the range is relative.
+    pub minutes: Option<i64>,
+}
+
+impl Range {
+    /// The last minutes until now.
+    pub fn last_minutes(minutes: i64) -> Self {
+        let until = Local::now();
+        Self {
+            since: until - Duration::minutes(minutes),
+            until,
+            period: None,
+            minutes: Some(minutes),
+        }
+    }
+
+    /// The calendar period containing the timestamp, in the configured time zone.
+    pub fn period(period: Period, timestamp: DateTime<Local>) -> Self {
+        let date = timezone::localize(&timestamp).naive_local().date();
+        let (since, until) = match period {
+            Period::Day => (date, date.succ()),
+            Period::Week => {
+                let since = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (since, since + Duration::days(7))
+            }
+            Period::Month => {
+                let since = date.with_day(1).unwrap();
+                let until = if since.month() == 12 {
+                    NaiveDate::from_ymd(since.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(since.year(), since.month() + 1, 1)
+                };
+                (since, until)
+            }
+            Period::Year => (
+                NaiveDate::from_ymd(date.year(), 1, 1),
+                NaiveDate::from_ymd(date.year() + 1, 1, 1),
+            ),
+        };
+        Self {
+            since: start_of_day(since),
+            until: start_of_day(until),
+            period: Some(period),
+            minutes: None,
+        }
+    }
+
+    /// The same-length range right before this one.
+    pub fn previous(&self) -> Self {
+        match self.period {
+            Some(period) => Self::period(period, self.since - Duration::milliseconds(1)),
+            None => Self {
+                since: self.since - self.duration(),
+                until: self.since,
+                period: None,
+                minutes: None,
+            },
+        }
+    }
+
+    /// The same-length range right after this one.
+    pub fn next(&self) -> Self {
+        match self.period {
+            Some(period) => Self::period(period, self.until),
+            None => Self {
+                since: self.until,
+                until: self.until + self.duration(),
+                period: None,
+                minutes: None,
+            },
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.until - self.since
+    }
+
+    /// Tells whether the range includes the current time, so that it should be updated live.
+    pub fn is_live(&self) -> bool {
+        self.until >= Local::now()
+    }
+
+    /// Formats the range start for the `datetime-local` input.
+    pub fn since_input(&self) -> String {
+        timezone::localize(&self.since)
+            .format(DATETIME_LOCAL_FORMAT)
+            .to_string()
+    }
+
+    /// Formats the range end for the `datetime-local` input.
+    pub fn until_input(&self) -> String {
+        timezone::localize(&self.until)
+            .format(DATETIME_LOCAL_FORMAT)
+            .to_string()
+    }
+
+    /// Parses the range from the decoded query items, ignoring the unrelated ones.
+    pub fn from_items(items: impl IntoIterator<Item = (String, String)>) -> StdResult<Self, String> {
+        let mut from = None;
+        let mut to = None;
+        let mut minutes = None;
+        let mut period = None;
+
+        for (key, value) in items {
+            match key.as_str() {
+                "from" if !value.is_empty() => from = Some(parse_timestamp(&value)?),
+                "to" if !value.is_empty() => to = Some(parse_timestamp(&value)?),
+                "minutes" if !value.is_empty() => {
+                    minutes = Some(value.parse::<i64>().map_err(|error| error.to_string())?)
+                }
+                "period" => {
+                    period = Some(match value.as_str() {
+                        "day" => Period::Day,
+                        "week" => Period::Week,
+                        "month" => Period::Month,
+                        "year" => Period::Year,
+                        _ => return Err(format!("unknown period: `{}`", value)),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(match (period, from, to) {
+            (Some(period), from, _) => Self::period(period, from.unwrap_or_else(Local::now)),
+            (None, Some(since), until) => {
+                let until = until.unwrap_or_else(Local::now);
+                if since >= until {
+                    return Err("`from` must be before `to`".into());
+                }
+                Self {
+                    since,
+                    until,
+                    period: None,
+                    minutes: None,
+                }
+            }
+            (None, None, _) => Self::last_minutes(minutes.unwrap_or(DEFAULT_MINUTES)),
+        })
+    }
+}
+
+impl<'f> FromForm<'f> for Range {
+    type Error = String;
+
+    fn from_form(items: &mut FormItems<'f>, _strict: bool) -> StdResult<Self, Self::Error> {
+        Self::from_items(items.map(|item| item.key_value_decoded()))
+    }
+}
+
+impl UriDisplay<Query> for Range {
+    fn fmt(&self, f: &mut Formatter<Query>) -> fmt::Result {
+        if let Some(minutes) = self.minutes {
+            return f.write_named_value("minutes", minutes);
+        }
+        if let Some(period) = self.period {
+            f.write_named_value("period", period)?;
+        } else {
+            f.write_named_value("to", self.until.timestamp_millis())?;
+        }
+        f.write_named_value("from", self.since.timestamp_millis())
+    }
+}
+
+impl UriDisplay<Query> for Period {
+    fn fmt(&self, f: &mut Formatter<Query>) -> fmt::Result {
+        f.write_value(match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+            Period::Year => "year",
+        })
+    }
+}
+
+/// Parses either Unix time in milliseconds or the `datetime-local` input value.
+pub fn parse_timestamp(value: &str) -> StdResult<DateTime<Local>, String> {
+    if let Ok(millis) = value.parse::<i64>() {
+        return Local
+            .timestamp_millis_opt(millis)
+            .single()
+            .ok_or_else(|| format!("timestamp is out of range: `{}`", value));
+    }
+    let datetime = NaiveDateTime::parse_from_str(value, DATETIME_LOCAL_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|error| format!("invalid timestamp `{}`: {}", value, error))?;
+    timezone::from_local(&datetime).ok_or_else(|| format!("non-existent local time: `{}`", value))
+}
+
+/// Returns the midnight of the date, or the earliest existing time if the midnight is skipped by DST.
+fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    (0..24)
+        .find_map(|hour| timezone::from_local(&date.and_hms(hour, 0, 0)))
+        .expect("no existing time within the day")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_range_ok() {
+        let range = Range::from_form(&mut FormItems::from("from=1000&to=3000"), true).unwrap();
+        assert_eq!(range.since, Local.timestamp_millis(1000));
+        assert_eq!(range.until, Local.timestamp_millis(3000));
+        assert_eq!(range.previous().since, Local.timestamp_millis(-1000));
+        assert_eq!(range.next().until, Local.timestamp_millis(5000));
+    }
+
+    #[test]
+    fn datetime_local_ok() {
+        let range = Range::from_form(
+            &mut FormItems::from("from=2020-12-01T10%3A00&to=2020-12-01T11%3A30"),
+            true,
+        );
+        assert_eq!(range.unwrap().duration(), Duration::minutes(90));
+    }
+
+    #[test]
+    fn inverted_range_err() {
+        assert!(Range::from_form(&mut FormItems::from("from=3000&to=1000"), true).is_err());
+    }
+
+    #[test]
+    fn out_of_range_timestamp_err() {
+        assert!(parse_timestamp(&i64::MAX.to_string()).is_err());
+    }
+
+    #[test]
+    fn month_ok() {
+        let range = Range::period(Period::Month, Local.ymd(2020, 12, 15).and_hms(12, 0, 0));
+        assert_eq!(range.since, Local.ymd(2020, 12, 1).and_hms(0, 0, 0));
+        assert_eq!(range.until, Local.ymd(2021, 1, 1).and_hms(0, 0, 0));
+        assert_eq!(range.next().until, Local.ymd(2021, 2, 1).and_hms(0, 0, 0));
+        assert_eq!(range.previous().since, Local.ymd(2020, 11, 1).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn week_ok() {
+        // December 16, 2020 is Wednesday.
+        let range = Range::period(Period::Week, Local.ymd(2020, 12, 16).and_hms(12, 0, 0));
+        assert_eq!(range.since, Local.ymd(2020, 12, 14).and_hms(0, 0, 0));
+        assert_eq!(range.until, Local.ymd(2020, 12, 21).and_hms(0, 0, 0));
+    }
+}
@@ -0,0 +1,194 @@
+//! Sensor search.
+
+use itertools::Itertools;
+use rocket::FromForm;
+
+use crate::core::db::sensor_summary::SensorSummary;
+use crate::prelude::*;
+
+/// Sensor search query, all the criteria are optional.
+#[derive(FromForm, Default, Clone, Debug)]
+pub struct SearchQuery {
+    /// Case-insensitive text to look for in the sensor ID, title and location.
+    pub q: Option<String>,
+
+    /// Exact location.
+    pub location: Option<String>,
+
+    /// Sensor ID prefix, for example a service ID.
+    pub prefix: Option<String>,
+
+    /// Value type, for example `Temperature`.
+    pub value_type: Option<String>,
+}
+
+/// Number of the found sensors per facet value.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct FacetValue {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Facets {
+    pub locations: Vec<FacetValue>,
+    pub prefixes: Vec<FacetValue>,
+    pub value_types: Vec<FacetValue>,
+}
+
+impl SearchQuery {
+    pub fn matches(&self, summary: &SensorSummary) -> bool {
+        self.matches_facets(summary) && self.matches_text(&summary.sensor)
+    }
+
+    /// Tells whether the search text of the sensor contains all the terms of `q`.
+    pub fn matches_text(&self, sensor: &Sensor) -> bool {
+        non_empty(&self.q).map_or(true, |q| {
+            let text = search_text(sensor);
+            q.to_lowercase().split_whitespace().all(|term| text.contains(term))
+        })
+    }
+
+    /// Tells whether the sensor matches the location, the prefix and the value type.
+    pub fn matches_facets(&self, summary: &SensorSummary) -> bool {
+        let sensor = &summary.sensor;
+        non_empty(&self.location).map_or(true, |location| sensor.location == location)
+            && non_empty(&self.prefix).map_or(true, |prefix| sensor.id.starts_with(prefix))
+            && non_empty(&self.value_type).map_or(true, |value_type| summary.reading.value.type_name() == value_type)
+    }
+
+    /// Encodes the non-empty criteria back into a query string.
+    pub fn to_query_string(&self) -> String {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for (key, value) in &[
+            ("q", &self.q),
+            ("location", &self.location),
+            ("prefix", &self.prefix),
+            ("value_type", &self.value_type),
+        ] {
+            if let Some(value) = non_empty(value) {
+                serializer.append_pair(key, value);
+            }
+        }
+        serializer.finish()
+    }
+}
+
+/// Returns the lowercase sensor ID, location and title, which the text query is matched against.
+///
+/// The search page filters the results by it in the browser, so that it doesn't reload them on every keystroke.
+pub fn search_text(sensor: &Sensor) -> String {
+    let mut text = format!("{} {}", sensor.id, sensor.location);
+    if let Some(title) = &sensor.title {
+        text.push(' ');
+        text.push_str(title);
+    }
+    text.to_lowercase()
+}
+
+/// Treats empty form fields as missing ones.
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|value| !value.is_empty())
+}
+
+impl Facets {
+    /// Counts the sensors per location, service prefix and value type, the most frequent first.
+    pub fn new<'a>(summaries: impl Iterator<Item = &'a SensorSummary> + Clone) -> Self {
+        Self {
+            locations: count(summaries.clone().map(|summary| summary.sensor.location.as_str())),
+            prefixes: count(summaries.clone().map(|summary| service_prefix(&summary.sensor.id))),
+            value_types: count(summaries.map(|summary| summary.reading.value.type_name())),
+        }
+    }
+}
+
+/// Returns the service ID part of the sensor ID including the separator,
+/// for instance: `tado::` for `tado::home::temperature`.
+pub fn service_prefix(sensor_id: &str) -> &str {
+    match sensor_id.find("::") {
+        Some(index) => &sensor_id[..index + 2],
+        None => sensor_id,
+    }
+}
+
+fn count<'a>(values: impl Iterator<Item = &'a str>) -> Vec<FacetValue> {
+    values
+        .sorted()
+        .group_by(|value| *value)
+        .into_iter()
+        .map(|(value, group)| FacetValue {
+            value: value.to_string(),
+            count: group.count(),
+        })
+        .sorted_by(|lhs, rhs| rhs.count.cmp(&lhs.count).then_with(|| lhs.value.cmp(&rhs.value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(sensor_id: &str, location: &str, value: Value) -> SensorSummary {
+        let message = Message::new(sensor_id).location(location).value(value);
+        SensorSummary {
+            sensor: message.sensor,
+            reading: message.reading,
+            is_hidden: false,
+            reading_count: 1,
+            reading_size: 0,
+        }
+    }
+
+    #[test]
+    fn matches_ok() {
+        let summary = summary("tado::home::temperature", "Living Room", Value::Temperature(21.0));
+        let query = SearchQuery {
+            q: Some("living TADO".into()),
+            value_type: Some("Temperature".into()),
+            ..Default::default()
+        };
+        assert!(query.matches(&summary));
+        let query = SearchQuery {
+            q: Some("kitchen".into()),
+            ..Default::default()
+        };
+        assert!(!query.matches(&summary));
+    }
+
+    #[test]
+    fn empty_fields_ignored() {
+        let summary = summary("tado::home::temperature", "Living Room", Value::Temperature(21.0));
+        let query = SearchQuery {
+            q: Some("".into()),
+            location: Some("".into()),
+            prefix: Some("tado::".into()),
+            ..Default::default()
+        };
+        assert!(query.matches(&summary));
+        assert_eq!(query.to_query_string(), "prefix=tado%3A%3A");
+    }
+
+    #[test]
+    fn facets_ok() {
+        let summaries = vec![
+            summary("tado::a", "Kitchen", Value::Temperature(21.0)),
+            summary("tado::b", "Bedroom", Value::Rh(50.0)),
+            summary("youless::c", "Kitchen", Value::Power(100.0)),
+        ];
+        let facets = Facets::new(summaries.iter());
+        assert_eq!(
+            facets.prefixes,
+            vec![
+                FacetValue {
+                    value: "tado::".into(),
+                    count: 2,
+                },
+                FacetValue {
+                    value: "youless::".into(),
+                    count: 1,
+                },
+            ]
+        );
+        assert_eq!(facets.locations[0].value, "Kitchen");
+    }
+}
@@ -1,29 +1,255 @@
 //! Web interface templates.
 
 use askama::Template;
+use itertools::Itertools;
+use rocket::http::uri::Uri;
 use rocket::uri;
 use serde_json::json;
 
+use crate::core::db::annotation::Annotation;
+use crate::core::db::media_reading::{MediaReading, MediaValue};
+use crate::core::db::sensor_summary::SensorSummary;
+use crate::core::db::statistics::Statistics;
+use crate::core::timezone;
 use crate::format::human_format;
 use crate::prelude::*;
-use crate::web::{rocket_uri_macro_delete_sensor, rocket_uri_macro_get_sensor_json, rocket_uri_macro_get_settings};
+use crate::settings::{TileSize, Widget};
+use crate::web::context::Context;
+use crate::web::dashboard::{SectionView, TileView};
+use crate::web::history::{page_count, HistoryQuery, SortKey};
+use crate::web::i18n;
+use crate::web::media::{media_kind, sniff_mime_type, MediaKind};
+use crate::web::range::{Period, Range, DATETIME_LOCAL_FORMAT};
+use crate::web::search::{search_text, Facets, SearchQuery};
+use crate::web::timeline::{Band, DutyCycle, Marker};
+use crate::web::{
+    rocket_uri_macro_delete_annotation, rocket_uri_macro_delete_sensor, rocket_uri_macro_get_gallery,
+    rocket_uri_macro_get_sensor, rocket_uri_macro_get_sensor_blob, rocket_uri_macro_get_sensor_history,
+    rocket_uri_macro_get_sensor_history_csv, rocket_uri_macro_get_sensor_json, rocket_uri_macro_get_sensors,
+    rocket_uri_macro_get_settings, rocket_uri_macro_post_annotation, rocket_uri_macro_post_login,
+    rocket_uri_macro_post_logout, rocket_uri_macro_post_sensors,
+};
 
 #[derive(Template)]
 #[template(path = "index.html")]
 pub struct IndexTemplate {
+    pub context: Context,
+
+    /// Dashboard IDs and titles.
+    pub dashboards: Vec<(String, String)>,
+
+    /// `WebPush` service IDs and VAPID public keys.
+    pub push_services: Vec<(String, String)>,
+
     #[allow(clippy::type_complexity)]
     pub actuals: Vec<(String, Vec<(Sensor, Reading)>)>,
 }
 
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+pub struct DashboardTemplate {
+    pub context: Context,
+    pub title: String,
+    pub sections: Vec<SectionView>,
+}
+
+#[derive(Template)]
+#[template(path = "comparison.html")]
+pub struct ComparisonTemplate {
+    pub context: Context,
+
+    /// Sensors with numeric values, which could be selected.
+    pub actuals: Vec<(Sensor, Reading)>,
+
+    pub sensor_ids: Vec<String>,
+
+    /// Chart period.
+    pub minutes: i64,
+
+    /// Stringified comparison chart, if any sensors are selected.
+    pub chart: Option<String>,
+}
+
+impl ComparisonTemplate {
+    fn is_selected(&self, sensor_id: &str) -> bool {
+        self.sensor_ids.iter().any(|selected_id| selected_id == sensor_id)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "search.html")]
+pub struct SearchTemplate {
+    pub context: Context,
+    pub query: SearchQuery,
+
+    /// Sensors matching the facets, the ones which don't match the text are hidden.
+    pub summaries: Vec<SensorSummary>,
+
+    pub facets: Facets,
+}
+
+impl SearchTemplate {
+    /// Returns the number of the sensors matching the text.
+    fn found_count(&self) -> usize {
+        self.summaries
+            .iter()
+            .filter(|summary| self.query.matches_text(&summary.sensor))
+            .count()
+    }
+
+    fn search_text(&self, sensor: &Sensor) -> String {
+        search_text(sensor)
+    }
+
+    /// Builds the search page URI which toggles the facet value.
+    fn facet_uri(&self, field: &str, value: &str) -> String {
+        let mut query = self.query.clone();
+        let criterion = match field {
+            "location" => &mut query.location,
+            "prefix" => &mut query.prefix,
+            _ => &mut query.value_type,
+        };
+        *criterion = match criterion {
+            Some(selected) if selected == value => None,
+            _ => Some(value.to_string()),
+        };
+        format!("search?{}", query.to_query_string())
+    }
+
+    fn is_facet_selected(&self, field: &str, value: &str) -> bool {
+        let criterion = match field {
+            "location" => &self.query.location,
+            "prefix" => &self.query.prefix,
+            _ => &self.query.value_type,
+        };
+        criterion.as_deref() == Some(value)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "gallery.html")]
+pub struct GalleryTemplate {
+    pub context: Context,
+    pub sensor: Sensor,
+
+    /// Images and videos within the range, the latest first.
+    pub readings: Vec<MediaReading>,
+
+    pub range: Range,
+}
+
+impl GalleryTemplate {
+    fn range_uri(&self, range: Range) -> String {
+        uri!(get_gallery: &self.sensor.id, range).to_string()
+    }
+
+    fn period_uri(&self, period: Period) -> String {
+        self.range_uri(Range::period(period, Local::now()))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "history.html")]
+pub struct HistoryTemplate {
+    pub context: Context,
+    pub sensor: Sensor,
+
+    /// The current page of the filtered and sorted readings.
+    pub readings: Vec<Reading>,
+
+    /// Number of the filtered readings on all the pages.
+    pub reading_count: usize,
+
+    pub query: HistoryQuery,
+}
+
+impl HistoryTemplate {
+    fn query_uri(&self, query: HistoryQuery) -> String {
+        uri!(get_sensor_history: &self.sensor.id, query).to_string()
+    }
+
+    fn csv_uri(&self) -> String {
+        uri!(get_sensor_history_csv: &self.sensor.id, self.query.with_page(1)).to_string()
+    }
+
+    fn range_uri(&self, range: Range) -> String {
+        self.query_uri(self.query.with_range(range))
+    }
+
+    fn period_uri(&self, period: Period) -> String {
+        self.range_uri(Range::period(period, Local::now()))
+    }
+
+    fn page_uri(&self, page: &usize) -> String {
+        self.query_uri(self.query.with_page(*page))
+    }
+
+    fn sort_uri(&self, sort: SortKey) -> String {
+        self.query_uri(self.query.with_sort(sort))
+    }
+
+    /// Returns the sorting icon for the column.
+    fn sort_icon(&self, sort: SortKey) -> &'static str {
+        match (self.query.sort == sort, self.query.descending) {
+            (false, _) => "fa-sort",
+            (true, true) => "fa-sort-down",
+            (true, false) => "fa-sort-up",
+        }
+    }
+
+    fn page_count(&self) -> usize {
+        page_count(self.reading_count)
+    }
+
+    fn is_current_page(&self, page: &usize) -> bool {
+        *page == self.query.page
+    }
+
+    /// Returns the first, the last and the neighbouring page numbers, `None` stands for an ellipsis.
+    fn pages(&self) -> Vec<Option<usize>> {
+        let page_count = self.page_count();
+        let mut pages = Vec::new();
+        for page in 1..=page_count {
+            if page == 1 || page == page_count || (page as i64 - self.query.page as i64).abs() <= 2 {
+                pages.push(Some(page));
+            } else if pages.last() != Some(&None) {
+                pages.push(None);
+            }
+        }
+        pages
+    }
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+pub struct LoginTemplate {
+    pub context: Context,
+
+    /// Whether the previous attempt has failed.
+    pub failed: bool,
+}
+
 #[derive(Template)]
 #[template(path = "settings.html")]
 pub struct SettingsTemplate {
+    pub context: Context,
+
     pub settings: String,
 }
 
+#[derive(Template)]
+#[template(path = "sensors.html")]
+pub struct SensorsTemplate {
+    pub context: Context,
+
+    pub summaries: Vec<SensorSummary>,
+}
+
 #[derive(Template)]
 #[template(path = "sensor.html")]
 pub struct SensorTemplate {
+    pub context: Context,
+
     pub sensor: Sensor,
     pub reading: Reading,
 
@@ -31,14 +257,48 @@ pub struct SensorTemplate {
     pub chart: String,
 
     /// Chart period.
-    pub minutes: i64,
+    pub range: Range,
+
+    /// Statistics within the period, `None` if the sensor isn't numeric.
+    pub statistics: Option<Statistics>,
+
+    /// Annotations of the sensor, its location and the global ones within the period.
+    pub annotations: Vec<Annotation>,
 
     pub reading_count: i64,
 }
 
 impl SensorTemplate {
-    /// Refresh a sensor view this many times per minute.
-    const AUTO_REFRESH_PER_MINUTE: i64 = 12;
+    fn gallery_uri(&self) -> String {
+        uri!(get_gallery: &self.sensor.id, self.range).to_string()
+    }
+
+    /// Formats the current time for the `datetime-local` input.
+    fn now_input(&self) -> String {
+        timezone::now().format(DATETIME_LOCAL_FORMAT).to_string()
+    }
+
+    fn history_uri(&self) -> String {
+        uri!(get_sensor_history: &self.sensor.id, HistoryQuery::new(self.range)).to_string()
+    }
+
+    /// Returns the sensor page URI with the specified range.
+    fn range_uri(&self, range: Range) -> String {
+        uri!(get_sensor: &self.sensor.id, range).to_string()
+    }
+
+    fn period_uri(&self, period: Period) -> String {
+        self.range_uri(Range::period(period, Local::now()))
+    }
+
+    /// Formats the statistical value in the chart units.
+    fn format_statistic<V: Borrow<f64>>(&self, value: V) -> String {
+        let value = value.borrow() * self.reading.value.chart_multiplier();
+        match self.reading.value.chart_unit() {
+            "" => format!("{:.2}", value),
+            unit => format!("{:.2} {}", value, unit),
+        }
+    }
 }
 
 /// Navigation bar.
@@ -62,11 +322,169 @@ struct SensorTilePartialTemplate<'a> {
 
     /// The latest reading.
     reading: &'a Reading,
+
+    /// Whether the write control should be rendered for the writable sensor.
+    is_admin: bool,
 }
 
 impl<'a> SensorTilePartialTemplate<'a> {
+    fn new(sensor: &'a Sensor, reading: &'a Reading, is_admin: bool) -> Self {
+        SensorTilePartialTemplate {
+            sensor,
+            reading,
+            is_admin,
+        }
+    }
+}
+
+/// Image viewer, video player or download link for the non-inline values, the value itself otherwise.
+#[derive(Template)]
+#[template(path = "partials/media.html")]
+struct MediaPartialTemplate<'a> {
+    sensor_id: &'a str,
+    timestamp: DateTime<Local>,
+
+    /// The value which is rendered as is, `None` for the media.
+    inline_value: Option<&'a Value>,
+
+    image_url: Option<&'a str>,
+
+    /// The blob MIME type and size.
+    mime_type: &'static str,
+    size: usize,
+}
+
+impl<'a> MediaPartialTemplate<'a> {
+    fn new(sensor_id: &'a str, reading: &'a Reading) -> Self {
+        let (image_url, mime_type, size) = match &reading.value {
+            Value::ImageUrl(url) => (Some(url.as_str()), "", 0),
+            Value::Blob(content) => (None, sniff_mime_type(content), content.len()),
+            _ => (None, "", 0),
+        };
+        MediaPartialTemplate {
+            sensor_id,
+            timestamp: reading.timestamp,
+            inline_value: Some(&reading.value).filter(|value| value.is_inline()),
+            image_url,
+            mime_type,
+            size,
+        }
+    }
+
+    /// Renders the gallery item, which is selected without the blob.
+    fn from_media(sensor_id: &'a str, reading: &'a MediaReading) -> Self {
+        let (image_url, mime_type, size) = match &reading.value {
+            MediaValue::ImageUrl(url) => (Some(url.as_str()), "", 0),
+            MediaValue::Blob { head, size } => (None, sniff_mime_type(head), *size),
+        };
+        MediaPartialTemplate {
+            sensor_id,
+            timestamp: reading.timestamp,
+            inline_value: None,
+            image_url,
+            mime_type,
+            size,
+        }
+    }
+
+    fn kind(&self) -> MediaKind {
+        match self.image_url {
+            Some(_) => MediaKind::Image,
+            None => media_kind(self.mime_type),
+        }
+    }
+
+    /// Returns the image URL or the blob URI relative to the `<base>` element.
+    fn url(&self) -> String {
+        match self.image_url {
+            Some(url) => url.to_string(),
+            None => {
+                let uri = uri!(get_sensor_blob: self.sensor_id, self.timestamp.timestamp_millis());
+                uri.to_string().trim_start_matches('/').to_string()
+            }
+        }
+    }
+
+    fn human_size(&self) -> String {
+        human_format(self.size as f64, "B")
+    }
+}
+
+/// User-defined dashboard tile.
+#[derive(Template)]
+#[template(path = "partials/dashboard_tile.html")]
+struct DashboardTilePartialTemplate<'a> {
+    view: &'a TileView,
+
+    /// The sensor, `None` if it doesn't exist.
+    sensor: Option<&'a Sensor>,
+
+    /// The latest reading, `None` if the sensor doesn't exist.
+    reading: Option<&'a Reading>,
+
+    /// Whether the write control should be rendered for the writable sensor.
+    is_admin: bool,
+}
+
+impl<'a> DashboardTilePartialTemplate<'a> {
+    fn new(view: &'a TileView, is_admin: bool) -> Self {
+        DashboardTilePartialTemplate {
+            view,
+            sensor: view.actual.as_ref().map(|(sensor, _)| sensor),
+            reading: view.actual.as_ref().map(|(_, reading)| reading),
+            is_admin,
+        }
+    }
+
+    /// Returns a [column size](https://bulma.io/documentation/columns/sizes/) of the tile.
+    fn column_width(&self) -> &'static str {
+        match self.view.tile.size {
+            TileSize::Small => "is-2",
+            TileSize::Medium => "is-3",
+            TileSize::Large => "is-4",
+            TileSize::Wide => "is-6",
+        }
+    }
+}
+
+/// Control to send a `Write` message to a writable sensor.
+#[derive(Template)]
+#[template(path = "partials/write_control.html")]
+struct WriteControlPartialTemplate<'a> {
+    sensor_id: &'a str,
+    value_type: &'static str,
+
+    /// Whether `Boolean` control is turned on.
+    is_on: bool,
+
+    /// The current value to pre-fill the control.
+    current: String,
+}
+
+impl<'a> WriteControlPartialTemplate<'a> {
     fn new(sensor: &'a Sensor, reading: &'a Reading) -> Self {
-        SensorTilePartialTemplate { sensor, reading }
+        WriteControlPartialTemplate {
+            sensor_id: &sensor.id,
+            value_type: reading.value.type_name(),
+            is_on: bool::try_from(&reading.value).unwrap_or_default(),
+            current: match &reading.value {
+                Value::StringEnum(value) => value.clone(),
+                value => f64::try_from(value).map(|value| value.to_string()).unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Returns the URL of the distinct values, which populate the `StringEnum` options.
+    fn values_url(&self) -> String {
+        format!("api/v1/sensors/{}/values", Uri::percent_encode(self.sensor_id))
+    }
+
+    /// Tells whether the value type has a control.
+    fn is_supported(&self) -> bool {
+        matches!(
+            self.value_type,
+            "Boolean" | "RelativeIntensity" | "Temperature" | "StringEnum"
+        )
     }
 }
 
@@ -101,6 +519,103 @@ impl F64ChartPartialTemplate {
     }
 }
 
+impl F64ChartPartialTemplate {
+    /// Stretches the time axis to the range, so that gaps at the start and at the end are visible.
+    pub fn range(mut self, range: &Range) -> Self {
+        self.chart["options"]["scales"]["xAxes"][0]["ticks"]["min"] = json!(range.since.timestamp_millis());
+        self.chart["options"]["scales"]["xAxes"][0]["ticks"]["max"] = json!(range.until.timestamp_millis());
+        self
+    }
+
+    /// Adds the annotation markers, which are drawn by the inline chart plugin.
+    pub fn annotations(mut self, annotations: &[Annotation]) -> Self {
+        self.chart["options"]["annotations"] = annotations
+            .iter()
+            .map(|annotation| {
+                json!({
+                    "since": annotation.since.timestamp_millis(),
+                    "until": annotation.until.timestamp_millis(),
+                    "text": annotation.text,
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Overlays the sensors on a shared time axis with a separate y-axis per unit.
+    pub fn new_comparison(series: &[(Sensor, Reading, Vec<Reading>)]) -> Self {
+        let units = series
+            .iter()
+            .map(|(_, reading, _)| reading.value.chart_unit())
+            .unique()
+            .collect_vec();
+        let y_axes = units
+            .iter()
+            .enumerate()
+            .map(|(i, unit)| {
+                json!({
+                    "id": unit,
+                    "display": true,
+                    "position": if i % 2 == 0 { "left" } else { "right" },
+                    "gridLines": {"drawOnChartArea": i == 0},
+                    "scaleLabel": {"display": !unit.is_empty(), "labelString": unit},
+                })
+            })
+            .collect_vec();
+        let datasets = series
+            .iter()
+            .enumerate()
+            .map(|(i, (sensor, reading, readings))| {
+                let multiplier = reading.value.chart_multiplier();
+                json!({
+                    "label": sensor.title(),
+                    "yAxisID": reading.value.chart_unit(),
+                    "borderColor": CHART_COLORS[i % CHART_COLORS.len()],
+                    "fill": false,
+                    "data": readings
+                        .iter()
+                        .filter_map(|reading| f64::try_from(&reading.value).ok().map(|value| json!({
+                            "x": reading.timestamp.timestamp_millis(),
+                            "y": value * multiplier,
+                        })))
+                        .collect::<serde_json::Value>(),
+                })
+            })
+            .collect_vec();
+        let mut options = chart_options("");
+        options["scales"]["yAxes"] = json!(y_axes);
+        options["tooltips"]["mode"] = json!("x");
+        F64ChartPartialTemplate {
+            chart: json!({
+                "type": "line",
+                "options": options,
+                "data": {"datasets": datasets},
+            }),
+        }
+    }
+}
+
+/// Dataset colors of the comparison chart.
+const CHART_COLORS: &[&str] = &[
+    "#209CEE", "#FF3860", "#23D160", "#FFDD57", "#7957D5", "#FF8A3D", "#00D1B2", "#363636",
+];
+
+/// State timeline of a `Boolean` or `StringEnum` sensor.
+#[derive(Template)]
+#[template(path = "partials/state_timeline.html")]
+pub struct StateTimelinePartialTemplate {
+    pub bands: Vec<Band>,
+    pub duty_cycles: Vec<DutyCycle>,
+}
+
+/// Event markers of a sensor which values couldn't be plotted.
+#[derive(Template)]
+#[template(path = "partials/event_markers.html")]
+pub struct EventMarkersPartialTemplate {
+    /// Markers, the latest first.
+    pub markers: Vec<Marker>,
+}
+
 fn chart_time_format() -> serde_json::Value {
     json!({
         "tooltipFormat": "MMM DD HH:mm:ss.SSS",
@@ -140,17 +655,45 @@ impl Value {
     pub fn is_inline(&self) -> bool {
         !matches!(self, Value::ImageUrl(_) | Value::Blob(..))
     }
+
+    /// Returns the units displayed on a chart, values with the same units share the y-axis.
+    pub fn chart_unit(&self) -> &'static str {
+        match self {
+            Value::DataSize(_) => "B",
+            Value::Temperature(_) => "℃",
+            Value::Bft(_) => "BFT",
+            Value::Rh(_) | Value::RelativeIntensity(_) | Value::Cloudiness(_) | Value::BatteryLife(_) => "%",
+            Value::Length(_) => "m",
+            Value::Duration(_) => "s",
+            Value::Energy(_) => "Wh",
+            Value::Power(_) => "W",
+            Value::Volume(_) => "㎥",
+            Value::Speed(_) => "m/s",
+            _ => "",
+        }
+    }
+
+    /// Returns the multiplier to convert the `f64` value into the units displayed on a chart.
+    pub fn chart_multiplier(&self) -> f64 {
+        if let Value::Energy(_) = self {
+            WH_IN_JOULE
+        } else {
+            1.0
+        }
+    }
 }
 
 impl std::fmt::Display for Value {
     /// Renders the value.
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let locale = i18n::current();
+        let number = |number: String| locale.localize_number(&number);
         match self {
             // language=HTML
-            Value::None => write!(f, r#"<i class="fas fa-question"></i> None"#),
+            Value::None => write!(f, r#"<i class="fas fa-question"></i> {}"#, locale.translate("None")),
 
             // language=HTML
-            Value::Other => write!(f, r#"<i class="fas fa-question"></i> Other"#),
+            Value::Other => write!(f, r#"<i class="fas fa-question"></i> {}"#, locale.translate("Other")),
 
             // language=HTML
             Value::Counter(count) => write!(f, r#"<i class="fas fa-sort-numeric-up-alt"></i> {}"#, count),
@@ -159,7 +702,7 @@ impl std::fmt::Display for Value {
             Value::DataSize(byte_number) => write!(
                 f,
                 r#"<i class="far fa-save"></i> {}"#,
-                human_format(*byte_number as f64, "B")
+                number(human_format(*byte_number as f64, "B"))
             ),
 
             // language=HTML
@@ -172,17 +715,21 @@ impl std::fmt::Display for Value {
             Value::Temperature(celsius) => write!(
                 f,
                 r#"<i class="fas fa-thermometer-half"></i> {}"#,
-                human_format(*celsius, "℃")
+                number(human_format(*celsius, "℃"))
             ),
 
             // language=HTML
             Value::Bft(force) => write!(f, r#"<i class="fas fa-wind"></i> {} BFT"#, force),
 
             // language=HTML
-            Value::Rh(percentage) => write!(f, r#"<i class="fas fa-water"></i> {}%"#, percentage),
+            Value::Rh(percentage) => write!(f, r#"<i class="fas fa-water"></i> {}%"#, number(percentage.to_string())),
 
             // language=HTML
-            Value::Length(meters) => write!(f, r#"<i class="fas fa-ruler"></i> {}"#, human_format(*meters, "m")),
+            Value::Length(meters) => write!(
+                f,
+                r#"<i class="fas fa-ruler"></i> {}"#,
+                number(human_format(*meters, "m"))
+            ),
 
             // language=HTML
             Value::ImageUrl(url) => write!(f, r#"<img src="{}" alt="">"#, url),
@@ -196,37 +743,55 @@ impl std::fmt::Display for Value {
                 } else {
                     r#"<i class="fas fa-toggle-off"></i>"#
                 },
-                if *flag { "Yes" } else { "No" }
+                locale.translate(if *flag { "Yes" } else { "No" })
             ),
 
             // language=HTML
-            Value::Duration(seconds) => write!(f, r#"<i class="far fa-clock"></i> {}"#, human_format(*seconds, "s")),
+            Value::Duration(seconds) => write!(
+                f,
+                r#"<i class="far fa-clock"></i> {}"#,
+                number(human_format(*seconds, "s"))
+            ),
 
             // language=HTML
-            Value::RelativeIntensity(percentage) => write!(f, r#"<i class="far fa-lightbulb"></i> {}%"#, percentage),
+            Value::RelativeIntensity(percentage) => write!(
+                f,
+                r#"<i class="far fa-lightbulb"></i> {}%"#,
+                number(percentage.to_string())
+            ),
 
             // language=HTML
             Value::Energy(joules) => write!(
                 f,
                 r#"<i class="fas fa-burn"></i> {}"#,
-                human_format(*joules / JOULES_IN_WH, "Wh")
+                number(human_format(*joules / JOULES_IN_WH, "Wh"))
             ),
 
             // language=HTML
-            Value::Power(watts) => write!(f, r#"<i class="fas fa-plug"></i> {}"#, human_format(*watts, "W")),
+            Value::Power(watts) => write!(
+                f,
+                r#"<i class="fas fa-plug"></i> {}"#,
+                number(human_format(*watts, "W"))
+            ),
 
             // language=HTML
-            Value::Volume(m3) => write!(f, r#"<i class="fas fa-oil-can"></i> {}"#, human_format(*m3, "㎥")),
+            Value::Volume(m3) => write!(
+                f,
+                r#"<i class="fas fa-oil-can"></i> {}"#,
+                number(human_format(*m3, "㎥"))
+            ),
 
             // language=HTML
             Value::Speed(speed) => write!(
                 f,
                 r#"<i class="fas fa-tachometer-alt"></i> {}"#,
-                human_format(*speed, "m/s")
+                number(human_format(*speed, "m/s"))
             ),
 
             // language=HTML
-            Value::Cloudiness(percentage) => write!(f, r#"<i class="fas fa-cloud"></i> {}%"#, percentage),
+            Value::Cloudiness(percentage) => {
+                write!(f, r#"<i class="fas fa-cloud"></i> {}%"#, number(percentage.to_string()))
+            }
 
             // language=HTML
             Value::BatteryLife(percentage) => write!(
@@ -243,18 +808,33 @@ impl std::fmt::Display for Value {
                 } else {
                     "empty"
                 },
-                percentage
+                number(percentage.to_string())
             ),
 
-            Value::Blob(..) => unimplemented!(),
+            // language=HTML
+            Value::Blob(content) => write!(
+                f,
+                r#"<i class="far fa-file"></i> {}"#,
+                number(human_format(content.len() as f64, "B"))
+            ),
         }
     }
 }
 
 impl Sensor {
-    /// Returns the sensor title or the sensor ID otherwise.
+    /// Returns the translated sensor title or the sensor ID otherwise.
     pub fn title(&self) -> String {
-        self.title.as_ref().unwrap_or(&self.id).into()
+        match &self.title {
+            Some(title) => i18n::current().translate(title).into_owned(),
+            None => self.id.clone(),
+        }
+    }
+}
+
+impl SensorSummary {
+    /// Returns the human-readable size of the stored readings.
+    pub fn human_reading_size(&self) -> String {
+        human_format(self.reading_size as f64, "B")
     }
 }
 
@@ -264,15 +844,53 @@ fn crate_version() -> &'static str {
 }
 
 /// Custom [Askama template filters](https://docs.rs/askama/0.9.0/askama/index.html#filters).
-mod filters {
+pub mod filters {
+    use crate::core::timezone;
     use crate::prelude::*;
+    use crate::web::i18n;
+
+    /// Strips the leading slash, so that the URI is resolved against the `<base>` element.
+    pub fn relative<U: std::fmt::Display>(uri: U) -> askama::Result<String> {
+        let uri = uri.to_string();
+        let uri = uri.trim_start_matches('/');
+        Ok(if uri.is_empty() { "./".into() } else { uri.into() })
+    }
+
+    /// Encodes the string to be used as a URL query parameter value.
+    pub fn query_value<S: AsRef<str>>(string: S) -> askama::Result<String> {
+        Ok(url::form_urlencoded::byte_serialize(string.as_ref().as_bytes()).collect())
+    }
 
     pub fn slug<S: AsRef<str>>(string: S) -> askama::Result<String> {
         Ok(slug::slugify(string))
     }
 
     pub fn format_datetime(datetime: &DateTime<Local>) -> askama::Result<String> {
-        Ok(datetime.format("%b %d, %H:%M:%S").to_string())
+        Ok(timezone::localize(datetime)
+            .format(i18n::current().datetime_format())
+            .to_string())
+    }
+
+    /// Formats the full timestamp with the time zone offset.
+    pub fn format_timestamp(datetime: &DateTime<Local>) -> askama::Result<String> {
+        Ok(timezone::localize(datetime).to_string())
+    }
+
+    /// Translates the text to the current locale.
+    pub fn t<S: AsRef<str>>(text: S) -> askama::Result<String> {
+        Ok(i18n::current().translate(text.as_ref()).into_owned())
+    }
+
+    /// Translates the text, which follows the count, in the plural form for the count.
+    pub fn t_plural<S: AsRef<str>>(count: &usize, text: S) -> askama::Result<String> {
+        Ok(i18n::current()
+            .translate_plural(text.as_ref(), *count as u64)
+            .into_owned())
+    }
+
+    /// Returns the translated plural forms to pick from with `Intl.PluralRules` in the browser.
+    pub fn plural_forms<S: AsRef<str>>(text: S) -> askama::Result<HashMap<&'static str, String>> {
+        Ok(i18n::current().plural_forms(text.as_ref()))
     }
 
     /// Returns a [column size](https://bulma.io/documentation/columns/sizes/) suitable to fit the value.
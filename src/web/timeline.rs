@@ -0,0 +1,228 @@
+//! State timelines and event markers for the non-numeric sensors.
+
+use crate::format::human_format;
+use crate::prelude::*;
+use crate::web::templates::filters;
+
+/// Color classes assigned to `StringEnum` states in the order of appearance.
+const STATE_COLOR_CLASSES: &[&str] = &[
+    "is-info",
+    "is-warning",
+    "is-primary",
+    "is-link",
+    "is-success",
+    "is-danger",
+];
+
+/// Continuous period of the same sensor state.
+#[derive(Debug, PartialEq)]
+pub struct Band {
+    pub state: String,
+    pub color_class: &'static str,
+    pub since: DateTime<Local>,
+    pub until: DateTime<Local>,
+
+    /// Offset from the period start, in percents.
+    pub left: f64,
+
+    /// Share of the period, in percents.
+    pub width: f64,
+}
+
+/// Total time spent in the state.
+#[derive(Debug, PartialEq)]
+pub struct DutyCycle {
+    pub state: String,
+    pub color_class: &'static str,
+    pub seconds: f64,
+
+    /// Share of the period, in percents.
+    pub percentage: f64,
+}
+
+/// Single event on the timeline.
+pub struct Marker {
+    pub timestamp: DateTime<Local>,
+
+    /// Offset from the period start, in percents.
+    pub left: f64,
+
+    pub text: String,
+}
+
+/// Tells whether the value is a discrete state, which is displayed as a timeline.
+pub fn is_state(value: &Value) -> bool {
+    state_of(value).is_some()
+}
+
+fn state_of(value: &Value) -> Option<String> {
+    match value {
+        Value::Boolean(true) => Some("On".into()),
+        Value::Boolean(false) => Some("Off".into()),
+        Value::StringEnum(state) => Some(state.clone()),
+        _ => None,
+    }
+}
+
+/// Builds the state bands within the period.
+///
+/// `initial` is the latest reading before the period, it defines the state at the period start.
+pub fn bands(
+    initial: Option<&Reading>,
+    readings: &[Reading],
+    since: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Vec<Band> {
+    let mut color_classes: Vec<(String, &'static str)> = Vec::new();
+    let mut bands: Vec<Band> = Vec::new();
+
+    let initial = initial.map(|reading| (since, &reading.value));
+    let changes = readings.iter().map(|reading| (reading.timestamp, &reading.value));
+    for (timestamp, value) in initial.into_iter().chain(changes) {
+        let state = match state_of(value) {
+            Some(state) => state,
+            None => continue,
+        };
+        if let Some(last) = bands.last_mut() {
+            if last.state == state {
+                continue;
+            }
+            last.until = timestamp;
+        }
+        let color_class = match value {
+            Value::Boolean(_) => filters::color_class(value).unwrap_or_default(),
+            _ => match color_classes.iter().find(|(known, _)| known == &state) {
+                Some((_, color_class)) => *color_class,
+                None => {
+                    let color_class = STATE_COLOR_CLASSES[color_classes.len() % STATE_COLOR_CLASSES.len()];
+                    color_classes.push((state.clone(), color_class));
+                    color_class
+                }
+            },
+        };
+        bands.push(Band {
+            state,
+            color_class,
+            since: timestamp,
+            until,
+            left: 0.0,
+            width: 0.0,
+        });
+    }
+
+    for band in bands.iter_mut() {
+        band.left = percentage(band.since - since, until - since);
+        band.width = percentage(band.until - band.since, until - since);
+    }
+    bands
+}
+
+/// Sums up the time spent in each state, the longest first.
+pub fn duty_cycles(bands: &[Band], since: DateTime<Local>, until: DateTime<Local>) -> Vec<DutyCycle> {
+    let mut duty_cycles: Vec<DutyCycle> = Vec::new();
+    for band in bands {
+        let seconds = (band.until - band.since).num_milliseconds() as f64 / 1000.0;
+        match duty_cycles.iter_mut().find(|duty_cycle| duty_cycle.state == band.state) {
+            Some(duty_cycle) => duty_cycle.seconds += seconds,
+            None => duty_cycles.push(DutyCycle {
+                state: band.state.clone(),
+                color_class: band.color_class,
+                seconds,
+                percentage: 0.0,
+            }),
+        }
+    }
+    let period_seconds = (until - since).num_milliseconds() as f64 / 1000.0;
+    for duty_cycle in duty_cycles.iter_mut() {
+        duty_cycle.percentage = if period_seconds > 0.0 {
+            duty_cycle.seconds / period_seconds * 100.0
+        } else {
+            0.0
+        };
+    }
+    duty_cycles.sort_by(|lhs, rhs| rhs.seconds.partial_cmp(&lhs.seconds).unwrap());
+    duty_cycles
+}
+
+/// Places the readings on the timeline.
+pub fn markers(readings: &[Reading], since: DateTime<Local>, until: DateTime<Local>) -> Vec<Marker> {
+    readings
+        .iter()
+        .map(|reading| Marker {
+            timestamp: reading.timestamp,
+            left: percentage(reading.timestamp - since, until - since),
+            text: match &reading.value {
+                Value::Text(text) => text.clone(),
+                value => format!("{:?}", value),
+            },
+        })
+        .collect()
+}
+
+fn percentage(part: chrono::Duration, total: chrono::Duration) -> f64 {
+    if total.num_milliseconds() > 0 {
+        (part.num_milliseconds() as f64 / total.num_milliseconds() as f64 * 100.0)
+            .max(0.0)
+            .min(100.0)
+    } else {
+        0.0
+    }
+}
+
+impl DutyCycle {
+    pub fn human_duration(&self) -> String {
+        human_format(self.seconds, "s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(timestamp: i64, value: Value) -> Reading {
+        Reading {
+            timestamp: Local.timestamp_millis(timestamp),
+            value,
+        }
+    }
+
+    #[test]
+    fn bands_ok() {
+        let initial = reading(0, Value::Boolean(false));
+        let readings = vec![
+            reading(2_000, Value::Boolean(true)),
+            reading(3_000, Value::Boolean(true)),
+            reading(6_000, Value::Boolean(false)),
+        ];
+        let bands = bands(
+            Some(&initial),
+            &readings,
+            Local.timestamp_millis(1_000),
+            Local.timestamp_millis(11_000),
+        );
+        assert_eq!(bands.len(), 3);
+        assert_eq!(bands[0].state, "Off");
+        assert!((bands[0].width - 10.0).abs() < 1e-6);
+        assert_eq!(bands[1].state, "On");
+        assert!((bands[1].left - 10.0).abs() < 1e-6);
+        assert!((bands[1].width - 40.0).abs() < 1e-6);
+        assert_eq!(bands[2].until, Local.timestamp_millis(11_000));
+    }
+
+    #[test]
+    fn duty_cycles_ok() {
+        let readings = vec![
+            reading(0, Value::StringEnum("heating".into())),
+            reading(3_000, Value::StringEnum("idle".into())),
+            reading(4_000, Value::StringEnum("heating".into())),
+        ];
+        let since = Local.timestamp_millis(0);
+        let until = Local.timestamp_millis(10_000);
+        let duty_cycles = duty_cycles(&bands(None, &readings, since, until), since, until);
+        assert_eq!(duty_cycles.len(), 2);
+        assert_eq!(duty_cycles[0].state, "heating");
+        assert!((duty_cycles[0].seconds - 9.0).abs() < 1e-6);
+        assert!((duty_cycles[0].percentage - 90.0).abs() < 1e-6);
+        assert!((duty_cycles[1].percentage - 10.0).abs() < 1e-6);
+    }
+}